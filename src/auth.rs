@@ -0,0 +1,68 @@
+use axum::{
+    Json,
+    extract::{Request, State},
+    http::{HeaderValue, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde_derive::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Bearer tokens accepted by the administrative API. With no tokens configured every
+/// administrative request is rejected.
+#[derive(Deserialize, Serialize, PartialEq, Clone, Debug, Default)]
+pub struct AuthConfig {
+    #[serde(default)]
+    pub tokens: Vec<String>,
+}
+
+impl AuthConfig {
+    /// Compares digests rather than the tokens themselves so the comparison takes the same time
+    /// however much of a guess is correct.
+    fn accepts(&self, presented: &str) -> bool {
+        let presented = Sha256::digest(presented.as_bytes());
+        self.tokens.iter().fold(false, |accepted, token| {
+            accepted | (Sha256::digest(token.as_bytes()) == presented)
+        })
+    }
+}
+
+/// Rejects requests that do not carry one of the configured tokens in an
+/// `Authorization: Bearer` header.
+pub async fn require_token(
+    State(auth): State<AuthConfig>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let presented = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    match presented {
+        Some(token) if auth.accepts(token) => next.run(request).await,
+        _ => (
+            StatusCode::UNAUTHORIZED,
+            [(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"))],
+            Json(serde_json::json!({ "errors": ["a valid bearer token is required"] })),
+        )
+            .into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_only_configured_tokens() {
+        let auth = AuthConfig {
+            tokens: vec!["first".to_string(), "second".to_string()],
+        };
+
+        assert!(auth.accepts("second"));
+        assert!(!auth.accepts("third"));
+        assert!(!AuthConfig::default().accepts(""));
+    }
+}
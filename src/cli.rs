@@ -0,0 +1,224 @@
+use std::io::Write;
+use thiserror::Error;
+
+use crate::config::AppConfig;
+use crate::params::{InvalidParam, Namespace, ProviderType};
+use crate::providers::{Backend, ProviderBackendError};
+use crate::types::ReleaseDiagnostic;
+
+/// What the binary was asked to do. Without arguments it serves the registry.
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Serve,
+    /// Prints every release of a provider with why it was accepted or skipped.
+    Diagnose {
+        namespace: Namespace,
+        provider_type: ProviderType,
+    },
+}
+
+#[derive(Error, Debug)]
+pub enum CliError {
+    #[error("usage: terraform-registry [diagnose <namespace> <type>]")]
+    Usage,
+    #[error(transparent)]
+    InvalidArgument(#[from] InvalidParam),
+    #[error("cannot diagnose provider: {0}")]
+    Backend(#[from] ProviderBackendError),
+    #[error("cannot write report: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl Command {
+    /// Parses the arguments following the program name.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, CliError> {
+        let args: Vec<String> = args.into_iter().collect();
+        match args.as_slice() {
+            [] => Ok(Self::Serve),
+            [command, namespace, provider_type] if command == "diagnose" => Ok(Self::Diagnose {
+                namespace: namespace.parse()?,
+                provider_type: provider_type.parse()?,
+            }),
+            _ => Err(CliError::Usage),
+        }
+    }
+}
+
+/// Builds the configured backend and writes its diagnosis of a provider to stdout. Backends block
+/// on their upstreams and some build blocking HTTP clients, so call this off the async runtime's
+/// threads.
+pub fn diagnose_configured(
+    config: &AppConfig,
+    namespace: Namespace,
+    provider_type: ProviderType,
+) -> Result<(), CliError> {
+    let backend = config.providers_backend()?;
+    diagnose(
+        backend.as_ref(),
+        namespace,
+        provider_type,
+        &mut std::io::stdout().lock(),
+    )
+}
+
+/// Writes one line per release followed by an indented line per asset.
+pub fn diagnose(
+    backend: &dyn Backend,
+    namespace: Namespace,
+    provider_type: ProviderType,
+    out: &mut impl Write,
+) -> Result<(), CliError> {
+    let releases = backend.diagnose_provider(namespace, provider_type)?;
+    for release in &releases {
+        write_release(out, release)?;
+    }
+    Ok(())
+}
+
+fn write_release(out: &mut impl Write, release: &ReleaseDiagnostic) -> std::io::Result<()> {
+    match (&release.version, &release.reason) {
+        (Some(version), _) => writeln!(out, "{}: accepted as {version}", release.tag)?,
+        (None, reason) => writeln!(
+            out,
+            "{}: rejected, {}",
+            release.tag,
+            reason.as_deref().unwrap_or("no reason given")
+        )?,
+    }
+    for asset in &release.assets {
+        match &asset.reason {
+            None => writeln!(out, "  {}: accepted", asset.name)?,
+            Some(reason) => writeln!(out, "  {}: rejected, {reason}", asset.name)?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::params::{Arch, Os, Version};
+    use crate::providers::{FakeBackend, Result};
+    use crate::types::{AssetDiagnostic, Package, VersionInfo};
+
+    struct DiagnosedBackend;
+
+    impl Backend for DiagnosedBackend {
+        fn name(&self) -> &'static str {
+            "diagnosed"
+        }
+
+        fn health(&self) -> Result<()> {
+            Ok(())
+        }
+
+        fn list_provider_versions(
+            &self,
+            _: Namespace,
+            _: ProviderType,
+        ) -> Result<Vec<VersionInfo>> {
+            Ok(Vec::new())
+        }
+
+        fn find_provider_package(
+            &self,
+            _: Namespace,
+            _: ProviderType,
+            _: Version,
+            _: Os,
+            _: Arch,
+        ) -> Result<Package> {
+            Err(ProviderBackendError::NotFound)
+        }
+
+        fn diagnose_provider(
+            &self,
+            _: Namespace,
+            _: ProviderType,
+        ) -> Result<Vec<ReleaseDiagnostic>> {
+            Ok(vec![
+                ReleaseDiagnostic {
+                    tag: "v1.0.0".to_string(),
+                    version: Some("1.0.0".to_string()),
+                    accepted: true,
+                    reason: None,
+                    assets: vec![
+                        AssetDiagnostic {
+                            name: "foo_1.0.0_linux_amd64.zip".to_string(),
+                            accepted: true,
+                            reason: None,
+                        },
+                        AssetDiagnostic {
+                            name: "foo_1.0.0_plan9_amd64.zip".to_string(),
+                            accepted: false,
+                            reason: Some("operating system `plan9` is not accepted".to_string()),
+                        },
+                    ],
+                },
+                ReleaseDiagnostic {
+                    tag: "nightly".to_string(),
+                    version: None,
+                    accepted: false,
+                    reason: Some("tag `nightly` is not a version".to_string()),
+                    assets: Vec::new(),
+                },
+            ])
+        }
+    }
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(Command::parse(Vec::new()).unwrap(), Command::Serve);
+        assert_eq!(
+            Command::parse(["diagnose", "acme", "foo"].map(String::from)).unwrap(),
+            Command::Diagnose {
+                namespace: "acme".parse().unwrap(),
+                provider_type: "foo".parse().unwrap(),
+            }
+        );
+        assert!(matches!(
+            Command::parse(["diagnose", "acme"].map(String::from)),
+            Err(CliError::Usage)
+        ));
+        assert!(matches!(
+            Command::parse(["diagnose", "acme", "foo_bar"].map(String::from)),
+            Err(CliError::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn reports_each_release_and_asset() {
+        let mut out = Vec::new();
+
+        diagnose(
+            &DiagnosedBackend,
+            "acme".parse().unwrap(),
+            "foo".parse().unwrap(),
+            &mut out,
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "v1.0.0: accepted as 1.0.0\n\
+             \x20 foo_1.0.0_linux_amd64.zip: accepted\n\
+             \x20 foo_1.0.0_plan9_amd64.zip: rejected, operating system `plan9` is not accepted\n\
+             nightly: rejected, tag `nightly` is not a version\n"
+        );
+    }
+
+    #[test]
+    fn backends_without_diagnostics_are_not_found() {
+        let result = diagnose(
+            &FakeBackend,
+            "acme".parse().unwrap(),
+            "foo".parse().unwrap(),
+            &mut Vec::new(),
+        );
+
+        assert!(matches!(
+            result,
+            Err(CliError::Backend(ProviderBackendError::NotFound))
+        ));
+    }
+}
@@ -1,14 +1,50 @@
+use crate::auth::AuthConfig;
+use crate::http_cache::HttpCacheConfig;
+use crate::index::IndexConfig;
+use crate::listing::ListingConfig;
+use crate::overlay::OverlayConfig;
 use crate::providers::Result as ProviderResult;
-use crate::providers::{Backend, FakeBackend, GitLabBackend};
+use crate::providers::{
+    AssetTemplate, Backend, FakeBackend, FilesystemStore, GitLabBackend, S3Store, StoreBackend,
+    TagPattern, WritableBackend,
+};
+use crate::publish::PublishConfig;
+use crate::shutdown::ShutdownConfig;
+use crate::telemetry::TelemetryConfig;
+use crate::tls::TlsConfig;
+use crate::types::GpgPublicKey;
 use config::Config;
 use serde_derive::{Deserialize, Serialize};
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::sync::Arc;
 
 #[derive(Deserialize, Serialize)]
 pub struct AppConfig {
     pub bind_address: SocketAddr,
     pub providers_backend: ProvidersBackend,
+    #[serde(default)]
+    pub cache: Option<CacheConfig>,
+    #[serde(default)]
+    pub http_cache: HttpCacheConfig,
+    #[serde(default)]
+    pub index: Option<IndexConfig>,
+    #[serde(default)]
+    pub overlay: Option<OverlayConfig>,
+    #[serde(default)]
+    pub listing: ListingConfig,
+    #[serde(default)]
+    pub platforms: PlatformsConfig,
+    #[serde(default)]
+    pub auth: AuthConfig,
+    #[serde(default)]
+    pub publish: PublishConfig,
+    #[serde(default)]
+    pub tls: Option<TlsConfig>,
+    #[serde(default)]
+    pub shutdown: ShutdownConfig,
+    #[serde(default)]
+    pub telemetry: TelemetryConfig,
 }
 
 #[derive(Deserialize, Serialize, PartialEq, Clone, Debug)]
@@ -16,6 +52,8 @@ pub struct AppConfig {
 pub enum ProvidersBackend {
     Fake,
     GitLabRelease(GitLabConfig),
+    Filesystem(FilesystemConfig),
+    S3(S3Config),
 }
 
 #[derive(Deserialize, Serialize, PartialEq, Clone, Debug)]
@@ -23,6 +61,128 @@ pub struct GitLabConfig {
     pub host: String,
     pub token: String,
     pub project: Option<String>,
+    /// For projects releasing several providers, which tags belong to which provider.
+    #[serde(default)]
+    pub tag_patterns: Vec<TagPatternConfig>,
+    /// How package assets are named, defaulting to
+    /// `terraform-provider-{type}_{version}_{os}_{arch}.zip`.
+    #[serde(default)]
+    pub asset_template: AssetTemplate,
+    /// Public keys the `SHA256SUMS` files of releases are signed with, advertised to Terraform
+    /// with every package.
+    #[serde(default)]
+    pub signing_keys: Vec<GpgPublicKey>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Clone, Debug)]
+pub struct TagPatternConfig {
+    pub namespace: String,
+    #[serde(rename = "type")]
+    pub provider_type: String,
+    /// Regular expression with one capture group for the version, e.g. `^provider-foo/v(.+)$`.
+    pub pattern: TagPattern,
+}
+
+/// Providers published through the upload API, stored as files below `root`
+#[derive(Deserialize, Serialize, PartialEq, Clone, Debug)]
+pub struct FilesystemConfig {
+    pub root: PathBuf,
+    /// URL `root` is reachable under. The registry serves it at `/files`.
+    pub base_url: String,
+}
+
+/// Providers published through the upload API, stored in an S3-compatible bucket
+#[derive(Deserialize, Serialize, PartialEq, Clone, Debug)]
+pub struct S3Config {
+    pub endpoint: String,
+    pub bucket: String,
+    #[serde(default = "default_s3_region")]
+    pub region: String,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    /// URL objects are downloaded from, defaulting to `{endpoint}/{bucket}`.
+    #[serde(default)]
+    pub public_url: Option<String>,
+}
+
+fn default_s3_region() -> String {
+    "us-east-1".to_string()
+}
+
+/// Operating systems and architectures packages are accepted for, by the upload API and when read
+/// from a backend
+#[derive(Deserialize, Serialize, PartialEq, Clone, Debug)]
+pub struct PlatformsConfig {
+    #[serde(default = "default_platform_os")]
+    pub os: Vec<String>,
+    #[serde(default = "default_platform_arch")]
+    pub arch: Vec<String>,
+}
+
+fn default_platform_os() -> Vec<String> {
+    [
+        "linux", "darwin", "windows", "freebsd", "openbsd", "netbsd", "solaris",
+    ]
+    .map(str::to_string)
+    .to_vec()
+}
+
+fn default_platform_arch() -> Vec<String> {
+    [
+        "386", "amd64", "arm", "arm64", "ppc64le", "s390x", "riscv64", "loong64",
+    ]
+    .map(str::to_string)
+    .to_vec()
+}
+
+impl Default for PlatformsConfig {
+    fn default() -> Self {
+        Self {
+            os: default_platform_os(),
+            arch: default_platform_arch(),
+        }
+    }
+}
+
+impl PlatformsConfig {
+    pub fn accepts_os(&self, os: &str) -> bool {
+        self.os.iter().any(|accepted| accepted == os)
+    }
+
+    pub fn accepts_arch(&self, arch: &str) -> bool {
+        self.arch.iter().any(|accepted| accepted == arch)
+    }
+}
+
+/// In-memory caching of backend results
+#[derive(Deserialize, Serialize, PartialEq, Clone, Debug)]
+pub struct CacheConfig {
+    #[serde(default = "default_cache_ttl_secs")]
+    pub ttl_secs: u64,
+    /// How long a `NotFound` answer is remembered.
+    #[serde(default = "default_cache_negative_ttl_secs")]
+    pub negative_ttl_secs: u64,
+    #[serde(default = "default_cache_max_entries")]
+    pub max_entries: usize,
+    /// How long past its TTL a provider listing may still be served while it is refreshed in the
+    /// background.
+    #[serde(default)]
+    pub stale_while_revalidate_secs: u64,
+    /// How long past its TTL a provider listing may still be served when the upstream fails.
+    #[serde(default)]
+    pub stale_if_error_secs: u64,
+}
+
+fn default_cache_ttl_secs() -> u64 {
+    60
+}
+
+fn default_cache_negative_ttl_secs() -> u64 {
+    10
+}
+
+fn default_cache_max_entries() -> usize {
+    1000
 }
 
 impl AppConfig {
@@ -36,7 +196,29 @@ impl AppConfig {
     pub fn providers_backend(&self) -> ProviderResult<Arc<dyn Backend>> {
         match &self.providers_backend {
             ProvidersBackend::Fake => Ok(Arc::new(FakeBackend)),
-            ProvidersBackend::GitLabRelease(cfg) => Ok(Arc::new(GitLabBackend::new(cfg.clone())?)),
+            ProvidersBackend::GitLabRelease(cfg) => Ok(Arc::new(GitLabBackend::new(
+                cfg.clone(),
+                self.platforms.clone(),
+            )?)),
+            ProvidersBackend::Filesystem(cfg) => Ok(Arc::new(StoreBackend::new(
+                FilesystemStore::new(cfg.clone()),
+            ))),
+            ProvidersBackend::S3(cfg) => {
+                Ok(Arc::new(StoreBackend::new(S3Store::new(cfg.clone())?)))
+            }
+        }
+    }
+
+    /// The backend the upload API publishes to, if the configured backend accepts uploads.
+    pub fn writable_backend(&self) -> ProviderResult<Option<Arc<dyn WritableBackend>>> {
+        match &self.providers_backend {
+            ProvidersBackend::Fake | ProvidersBackend::GitLabRelease(_) => Ok(None),
+            ProvidersBackend::Filesystem(cfg) => Ok(Some(Arc::new(StoreBackend::new(
+                FilesystemStore::new(cfg.clone()),
+            )))),
+            ProvidersBackend::S3(cfg) => Ok(Some(Arc::new(StoreBackend::new(S3Store::new(
+                cfg.clone(),
+            )?)))),
         }
     }
 }
@@ -44,6 +226,9 @@ impl AppConfig {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::index::ProviderRef;
+    use crate::signing::SigningConfig;
+    use crate::telemetry::LogFormat;
     use serde_yml as yaml;
 
     #[test]
@@ -55,8 +240,48 @@ providers_backend:
 
         let config: AppConfig = yaml::from_str(yaml).unwrap();
 
-        assert_eq!(config.bind_address, SocketAddr::from(([127, 0, 0, 1], 8000)));
+        assert_eq!(
+            config.bind_address,
+            SocketAddr::from(([127, 0, 0, 1], 8000))
+        );
         assert_eq!(config.providers_backend, ProvidersBackend::Fake);
+        assert_eq!(config.cache, None);
+        assert_eq!(config.http_cache, HttpCacheConfig::default());
+        assert_eq!(config.index, None);
+        assert_eq!(config.tls, None);
+        assert_eq!(config.shutdown, ShutdownConfig::default());
+        assert_eq!(config.telemetry, TelemetryConfig::default());
+        assert_eq!(config.platforms, PlatformsConfig::default());
+    }
+
+    #[test]
+    fn test_config_platforms() {
+        let yaml = "\
+bind_address: '127.0.0.1:8000'
+providers_backend:
+  type: fake
+platforms:
+  arch: [amd64, arm64]";
+
+        let config: AppConfig = yaml::from_str(yaml).unwrap();
+
+        assert!(config.platforms.accepts_os("netbsd"));
+        assert!(config.platforms.accepts_arch("arm64"));
+        assert!(!config.platforms.accepts_arch("386"));
+    }
+
+    #[test]
+    fn test_config_listing() {
+        let yaml = "\
+bind_address: '127.0.0.1:8000'
+providers_backend:
+  type: fake
+listing:
+  hide_prereleases: true";
+
+        let config: AppConfig = yaml::from_str(yaml).unwrap();
+
+        assert!(config.listing.hide_prereleases);
     }
 
     #[test]
@@ -71,14 +296,248 @@ providers_backend:
 
         let config: AppConfig = yaml::from_str(yaml).unwrap();
 
-        assert_eq!(config.bind_address, SocketAddr::from(([127, 0, 0, 1], 8000)));
+        assert_eq!(
+            config.bind_address,
+            SocketAddr::from(([127, 0, 0, 1], 8000))
+        );
         assert_eq!(
             config.providers_backend,
             ProvidersBackend::GitLabRelease(GitLabConfig {
                 host: "gitlab.example.com".to_string(),
                 token: "secret-token".to_string(),
                 project: Some("my-project".to_string()),
+                tag_patterns: Vec::new(),
+                asset_template: AssetTemplate::default(),
+                signing_keys: Vec::new(),
             })
         );
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_config_gitlab_tag_patterns() {
+        let yaml = r"
+bind_address: '127.0.0.1:8000'
+providers_backend:
+  type: git_lab_release
+  host: gitlab.example.com
+  token: secret-token
+  project: monorepo
+  asset_template: '{type}-{version}-{os}-{arch}.zip'
+  tag_patterns:
+    - namespace: acme
+      type: foo
+      pattern: '^provider-foo/v(.+)$'";
+        let invalid = yaml.replace("(.+)", ".+");
+
+        let config: AppConfig = yaml::from_str(yaml).unwrap();
+
+        let ProvidersBackend::GitLabRelease(gitlab) = config.providers_backend else {
+            panic!("expected a GitLab backend");
+        };
+        assert_eq!(
+            gitlab.tag_patterns,
+            vec![TagPatternConfig {
+                namespace: "acme".to_string(),
+                provider_type: "foo".to_string(),
+                pattern: TagPattern::new("^provider-foo/v(.+)$").unwrap(),
+            }]
+        );
+        assert_eq!(
+            gitlab.asset_template,
+            AssetTemplate::new("{type}-{version}-{os}-{arch}.zip").unwrap()
+        );
+        assert!(yaml::from_str::<AppConfig>(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_config_tls() {
+        let yaml = "\
+bind_address: '0.0.0.0:8443'
+providers_backend:
+  type: fake
+tls:
+  cert_path: /etc/registry/tls.crt
+  key_path: /etc/registry/tls.key
+  client_ca_path: /etc/registry/ca.crt";
+
+        let config: AppConfig = yaml::from_str(yaml).unwrap();
+
+        assert_eq!(
+            config.tls,
+            Some(TlsConfig {
+                cert_path: "/etc/registry/tls.crt".into(),
+                key_path: "/etc/registry/tls.key".into(),
+                client_ca_path: Some("/etc/registry/ca.crt".into()),
+                reload_interval_secs: 60,
+            })
+        );
+    }
+
+    #[test]
+    fn test_config_shutdown() {
+        let yaml = "\
+bind_address: '127.0.0.1:8000'
+providers_backend:
+  type: fake
+shutdown:
+  shutdown_delay_secs: 5";
+
+        let config: AppConfig = yaml::from_str(yaml).unwrap();
+
+        assert_eq!(
+            config.shutdown,
+            ShutdownConfig {
+                shutdown_delay_secs: 5,
+                drain_timeout_secs: 30,
+            }
+        );
+    }
+
+    #[test]
+    fn test_config_telemetry() {
+        let yaml = "\
+bind_address: '127.0.0.1:8000'
+providers_backend:
+  type: fake
+telemetry:
+  log_format: json
+  otlp:
+    endpoint: http://otel-collector:4318/v1/traces";
+
+        let config: AppConfig = yaml::from_str(yaml).unwrap();
+        assert_eq!(config.telemetry.log_format, LogFormat::Json);
+        let otlp = config.telemetry.otlp.unwrap();
+
+        assert_eq!(otlp.endpoint, "http://otel-collector:4318/v1/traces");
+        assert_eq!(otlp.service_name, "terraform-registry");
+    }
+
+    #[test]
+    fn test_config_cache() {
+        let yaml = "\
+bind_address: '127.0.0.1:8000'
+providers_backend:
+  type: fake
+cache:
+  ttl_secs: 300
+  stale_if_error_secs: 86400
+http_cache:
+  versions: public, max-age=300";
+
+        let config: AppConfig = yaml::from_str(yaml).unwrap();
+
+        assert_eq!(
+            config.cache,
+            Some(CacheConfig {
+                ttl_secs: 300,
+                negative_ttl_secs: 10,
+                max_entries: 1000,
+                stale_while_revalidate_secs: 0,
+                stale_if_error_secs: 86400,
+            })
+        );
+        assert_eq!(config.http_cache.versions, "public, max-age=300");
+        assert_eq!(config.http_cache.download, "no-cache");
+    }
+
+    #[test]
+    fn test_config_index() {
+        let yaml = "\
+bind_address: '127.0.0.1:8000'
+providers_backend:
+  type: fake
+index:
+  path: /var/lib/registry/index.db
+  providers:
+    - namespace: acme
+      type: foo
+overlay:
+  path: /var/lib/registry/overlay.json";
+
+        let config: AppConfig = yaml::from_str(yaml).unwrap();
+
+        assert_eq!(
+            config.index,
+            Some(IndexConfig {
+                path: "/var/lib/registry/index.db".into(),
+                sync_interval_secs: 300,
+                providers: vec![ProviderRef {
+                    namespace: "acme".parse().unwrap(),
+                    provider_type: "foo".parse().unwrap(),
+                }],
+                verify_packages: None,
+            })
+        );
+        assert_eq!(
+            config.overlay,
+            Some(OverlayConfig {
+                path: "/var/lib/registry/overlay.json".into(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_config_filesystem_backend_with_uploads() {
+        let yaml = "\
+bind_address: '127.0.0.1:8000'
+providers_backend:
+  type: filesystem
+  root: /var/lib/registry/providers
+  base_url: https://registry.example.com/files
+auth:
+  tokens: [secret]
+publish:
+  max_upload_bytes: 1048576
+  signing:
+    key_path: /etc/registry/signing.asc
+    namespaces: [acme]";
+
+        let config: AppConfig = yaml::from_str(yaml).unwrap();
+
+        assert_eq!(
+            config.providers_backend,
+            ProvidersBackend::Filesystem(FilesystemConfig {
+                root: "/var/lib/registry/providers".into(),
+                base_url: "https://registry.example.com/files".to_string(),
+            })
+        );
+        assert_eq!(config.auth.tokens, vec!["secret"]);
+        assert_eq!(config.publish.max_upload_bytes, 1_048_576);
+        assert_eq!(
+            config.publish.signing,
+            Some(SigningConfig {
+                key_path: "/etc/registry/signing.asc".into(),
+                passphrase: None,
+                namespaces: vec!["acme".to_string()],
+            })
+        );
+        assert!(config.writable_backend().unwrap().is_some());
+    }
+
+    #[test]
+    fn test_config_s3_backend() {
+        let yaml = "\
+bind_address: '127.0.0.1:8000'
+providers_backend:
+  type: s3
+  endpoint: https://s3.example.com
+  bucket: providers
+  access_key_id: id
+  secret_access_key: secret";
+
+        let config: AppConfig = yaml::from_str(yaml).unwrap();
+
+        assert_eq!(
+            config.providers_backend,
+            ProvidersBackend::S3(S3Config {
+                endpoint: "https://s3.example.com".to_string(),
+                bucket: "providers".to_string(),
+                region: "us-east-1".to_string(),
+                access_key_id: "id".to_string(),
+                secret_access_key: "secret".to_string(),
+                public_url: None,
+            })
+        );
+        assert_eq!(config.auth, AuthConfig::default());
+    }
+}
@@ -0,0 +1,284 @@
+use semver::Version;
+use std::str::FromStr;
+use thiserror::Error;
+
+use crate::types::VersionInfo;
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum ConstraintError {
+    #[error("version constraint is empty")]
+    Empty,
+    #[error("`{0}` is not a valid version constraint")]
+    Invalid(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operator {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    /// `~>`: at least the given version, allowing only its last given part to increase.
+    Pessimistic,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Term {
+    operator: Operator,
+    version: Version,
+    /// How many of major, minor and patch were written; `~> 1.2` differs from `~> 1.2.0`.
+    parts: usize,
+}
+
+/// A Terraform version constraint such as `~> 1.2, != 1.2.5`. Every comma-separated term must
+/// hold. As in Terraform, prereleases only match a term naming them exactly.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VersionConstraint {
+    terms: Vec<Term>,
+}
+
+impl FromStr for VersionConstraint {
+    type Err = ConstraintError;
+
+    fn from_str(constraint: &str) -> Result<Self, Self::Err> {
+        if constraint.trim().is_empty() {
+            return Err(ConstraintError::Empty);
+        }
+
+        let terms = constraint
+            .split(',')
+            .map(|term| {
+                parse_term(term.trim())
+                    .ok_or_else(|| ConstraintError::Invalid(term.trim().to_string()))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { terms })
+    }
+}
+
+fn parse_term(term: &str) -> Option<Term> {
+    // Longer operators first, so `>=` is not read as `>` followed by `=1.0`.
+    let (operator, rest) = [
+        ("~>", Operator::Pessimistic),
+        (">=", Operator::Ge),
+        ("<=", Operator::Le),
+        ("!=", Operator::Ne),
+        (">", Operator::Gt),
+        ("<", Operator::Lt),
+        ("=", Operator::Eq),
+    ]
+    .into_iter()
+    .find_map(|(prefix, operator)| term.strip_prefix(prefix).map(|rest| (operator, rest)))
+    .unwrap_or((Operator::Eq, term));
+
+    let (version, parts) = parse_partial(rest.trim())?;
+    Some(Term {
+        operator,
+        version,
+        parts,
+    })
+}
+
+/// Reads `1`, `1.2` or a full semantic version, filling missing parts with zeros.
+fn parse_partial(version: &str) -> Option<(Version, usize)> {
+    if let Ok(full) = Version::parse(version) {
+        return Some((full, 3));
+    }
+
+    let parts = version
+        .split('.')
+        .map(|part| part.parse::<u64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    match parts.as_slice() {
+        [major] => Some((Version::new(*major, 0, 0), 1)),
+        [major, minor] => Some((Version::new(*major, *minor, 0), 2)),
+        _ => None,
+    }
+}
+
+impl Term {
+    fn matches(&self, version: &Version) -> bool {
+        match self.operator {
+            Operator::Eq => version == &self.version,
+            Operator::Ne => version != &self.version,
+            Operator::Gt => version > &self.version,
+            Operator::Ge => version >= &self.version,
+            Operator::Lt => version < &self.version,
+            Operator::Le => version <= &self.version,
+            Operator::Pessimistic => {
+                let fixed = [self.version.major, self.version.minor];
+                let actual = [version.major, version.minor];
+                let pinned = self.parts.saturating_sub(1);
+                version >= &self.version && fixed[..pinned] == actual[..pinned]
+            }
+        }
+    }
+}
+
+impl VersionConstraint {
+    pub fn matches(&self, version: &Version) -> bool {
+        if !version.pre.is_empty()
+            && !self
+                .terms
+                .iter()
+                .any(|term| term.operator == Operator::Eq && &term.version == version)
+        {
+            return false;
+        }
+        self.terms.iter().all(|term| term.matches(version))
+    }
+}
+
+/// The highest version that is not a prerelease, which is what Terraform picks without a
+/// constraint.
+pub fn latest<'a>(versions: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    versions
+        .into_iter()
+        .filter_map(|version| Some((Version::parse(version).ok()?, version)))
+        .filter(|(parsed, _)| parsed.pre.is_empty())
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(_, version)| version)
+}
+
+/// What the `constraint` query parameter of a version listing asks for.
+#[derive(Debug, Clone, PartialEq)]
+pub enum VersionQuery {
+    /// Only the version `latest` would pick.
+    Latest,
+    Matching(VersionConstraint),
+}
+
+impl FromStr for VersionQuery {
+    type Err = ConstraintError;
+
+    fn from_str(query: &str) -> Result<Self, Self::Err> {
+        match query.trim() {
+            "latest" => Ok(Self::Latest),
+            constraint => constraint.parse().map(Self::Matching),
+        }
+    }
+}
+
+impl VersionQuery {
+    /// Keeps the versions asked for, along with the latest of them. Versions that are not valid
+    /// semantic versions never match.
+    pub fn select(&self, versions: Vec<VersionInfo>) -> (Vec<VersionInfo>, Option<String>) {
+        let selected: Vec<VersionInfo> = match self {
+            Self::Latest => {
+                let latest =
+                    latest(versions.iter().map(|v| v.version.as_str())).map(str::to_string);
+                versions
+                    .into_iter()
+                    .filter(|v| Some(&v.version) == latest.as_ref())
+                    .collect()
+            }
+            Self::Matching(constraint) => versions
+                .into_iter()
+                .filter(|v| {
+                    Version::parse(&v.version).is_ok_and(|version| constraint.matches(&version))
+                })
+                .collect(),
+        };
+        let latest = latest(selected.iter().map(|v| v.version.as_str())).map(str::to_string);
+        (selected, latest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matching<'a>(constraint: &str, versions: &[&'a str]) -> Vec<&'a str> {
+        let constraint: VersionConstraint = constraint.parse().unwrap();
+        versions
+            .iter()
+            .copied()
+            .filter(|version| constraint.matches(&Version::parse(version).unwrap()))
+            .collect()
+    }
+
+    const VERSIONS: &[&str] = &[
+        "1.1.9",
+        "1.2.0",
+        "1.2.4",
+        "1.2.5",
+        "1.3.0",
+        "2.0.0-beta.1",
+        "2.0.0",
+    ];
+
+    #[test]
+    fn pessimistic_constraints_pin_all_but_the_last_part() {
+        assert_eq!(
+            matching("~>1.2,!=1.2.5", VERSIONS),
+            vec!["1.2.0", "1.2.4", "1.3.0"]
+        );
+        assert_eq!(
+            matching("~> 1.2.0", VERSIONS),
+            vec!["1.2.0", "1.2.4", "1.2.5"]
+        );
+        assert_eq!(
+            matching("~> 1", VERSIONS),
+            vec!["1.1.9", "1.2.0", "1.2.4", "1.2.5", "1.3.0", "2.0.0"]
+        );
+    }
+
+    #[test]
+    fn comparisons_and_exact_versions() {
+        assert_eq!(
+            matching(">= 1.2.4, < 2", VERSIONS),
+            vec!["1.2.4", "1.2.5", "1.3.0"]
+        );
+        assert_eq!(matching("1.2.5", VERSIONS), vec!["1.2.5"]);
+        assert_eq!(matching("= 1.2", VERSIONS), vec!["1.2.0"]);
+    }
+
+    #[test]
+    fn prereleases_need_an_exact_match() {
+        assert_eq!(matching(">= 2.0.0-alpha", VERSIONS), vec!["2.0.0"]);
+        assert_eq!(matching("2.0.0-beta.1", VERSIONS), vec!["2.0.0-beta.1"]);
+    }
+
+    #[test]
+    fn rejects_malformed_constraints() {
+        assert_eq!("".parse::<VersionConstraint>(), Err(ConstraintError::Empty));
+        assert_eq!(
+            "~> 1.x".parse::<VersionConstraint>(),
+            Err(ConstraintError::Invalid("~> 1.x".to_string()))
+        );
+        assert!(">= 1.0,".parse::<VersionConstraint>().is_err());
+    }
+
+    #[test]
+    fn latest_skips_prereleases() {
+        assert_eq!(latest(VERSIONS.iter().copied()), Some("2.0.0"));
+        assert_eq!(latest(["1.0.0", "1.1.0-rc.1"]), Some("1.0.0"));
+        assert_eq!(latest(["1.1.0-rc.1"]), None);
+    }
+
+    #[test]
+    fn queries_select_versions_and_their_latest() {
+        let versions: Vec<VersionInfo> = VERSIONS
+            .iter()
+            .map(|version| VersionInfo {
+                version: (*version).to_string(),
+                protocols: vec!["5.0".to_string()],
+                platforms: Vec::new(),
+                deprecation: None,
+            })
+            .collect();
+
+        let (selected, newest) = "~> 1.2"
+            .parse::<VersionQuery>()
+            .unwrap()
+            .select(versions.clone());
+        let (only, alias) = "latest".parse::<VersionQuery>().unwrap().select(versions);
+
+        assert_eq!(selected.len(), 4);
+        assert_eq!(newest.as_deref(), Some("1.3.0"));
+        assert_eq!(only.len(), 1);
+        assert_eq!(alias.as_deref(), Some("2.0.0"));
+    }
+}
@@ -0,0 +1,178 @@
+use axum::{
+    Json,
+    extract::State,
+    response::{IntoResponse, Response},
+};
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use crate::constraint;
+use crate::listing::{self, ListingConfig};
+use crate::params::{Namespace, ProviderType, ValidPath};
+use crate::providers::{Backend, ProviderBackendError, ProviderDetails};
+use crate::routes::call_backend;
+use crate::types::{NamespacesResponse, ProviderMetadata, ProvidersResponse, VersionInfo};
+
+/// List every namespace holding a provider the backend can enumerate
+pub async fn list_namespaces_handler(State(backend): State<Arc<dyn Backend>>) -> Response {
+    match call_backend(backend, |backend| backend.list_providers()).await {
+        Ok(providers) => {
+            let namespaces: BTreeSet<String> = providers
+                .into_iter()
+                .map(|provider| provider.namespace.into())
+                .collect();
+            Json(NamespacesResponse {
+                namespaces: namespaces.into_iter().collect(),
+            })
+            .into_response()
+        }
+        Err(error) => error.into_response(),
+    }
+}
+
+/// List the providers within one namespace, answering 404 when it holds none
+pub async fn list_providers_handler(
+    State(backend): State<Arc<dyn Backend>>,
+    ValidPath(namespace): ValidPath<Namespace>,
+) -> Response {
+    match call_backend(backend, |backend| backend.list_providers()).await {
+        Ok(providers) => {
+            let providers: BTreeSet<String> = providers
+                .into_iter()
+                .filter(|provider| provider.namespace == namespace)
+                .map(|provider| provider.provider_type.into())
+                .collect();
+            if providers.is_empty() {
+                return ProviderBackendError::NotFound.into_response();
+            }
+            Json(ProvidersResponse {
+                namespace: namespace.into(),
+                providers: providers.into_iter().collect(),
+            })
+            .into_response()
+        }
+        Err(error) => error.into_response(),
+    }
+}
+
+/// Describe one provider along with its latest version
+pub async fn provider_metadata_handler(
+    State(backend): State<Arc<dyn Backend>>,
+    State(listing): State<ListingConfig>,
+    ValidPath((namespace, provider_type)): ValidPath<(Namespace, ProviderType)>,
+) -> Response {
+    let (ns, pt) = (namespace.clone(), provider_type.clone());
+    let result = call_backend(backend, move |backend| {
+        let versions = backend.list_provider_versions(ns.clone(), pt.clone())?;
+        let details = backend.describe_provider(ns, pt)?;
+        Ok((versions, details))
+    })
+    .await;
+
+    match result {
+        Ok((versions, details)) => Json(provider_metadata(
+            namespace,
+            provider_type,
+            listing::normalize(versions, &listing),
+            details,
+        ))
+        .into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+/// The latest version is the one Terraform would pick, or the newest listed when every version
+/// is a prerelease.
+fn provider_metadata(
+    namespace: Namespace,
+    provider_type: ProviderType,
+    versions: Vec<VersionInfo>,
+    details: ProviderDetails,
+) -> ProviderMetadata {
+    let stable =
+        constraint::latest(versions.iter().map(|v| v.version.as_str())).map(str::to_string);
+    let latest = versions
+        .into_iter()
+        .rev()
+        .find(|v| stable.as_ref().is_none_or(|stable| &v.version == stable));
+
+    ProviderMetadata {
+        namespace: namespace.into(),
+        provider_type: provider_type.into(),
+        description: details.description,
+        source: details.source,
+        tags: details.tags,
+        published_at: latest
+            .as_ref()
+            .and_then(|v| details.published.get(&v.version).cloned()),
+        latest_version: latest.as_ref().map(|v| v.version.clone()),
+        platforms: latest.map(|v| v.platforms).unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Platform;
+    use std::collections::BTreeMap;
+
+    fn version(name: &str, os: &str) -> VersionInfo {
+        VersionInfo {
+            version: name.to_string(),
+            protocols: vec!["5.0".to_string()],
+            platforms: vec![Platform {
+                os: os.to_string(),
+                arch: "amd64".to_string(),
+            }],
+            deprecation: None,
+        }
+    }
+
+    #[test]
+    fn metadata_describes_the_latest_stable_version() {
+        let details = ProviderDetails {
+            description: Some("Example provider".to_string()),
+            source: Some("https://gitlab.example.com/acme/foo".to_string()),
+            tags: vec!["networking".to_string()],
+            published: BTreeMap::from([
+                ("1.0.0".to_string(), "2024-01-01T00:00:00Z".to_string()),
+                ("1.1.0".to_string(), "2024-02-01T00:00:00Z".to_string()),
+            ]),
+        };
+
+        let metadata = provider_metadata(
+            "acme".parse().unwrap(),
+            "foo".parse().unwrap(),
+            vec![
+                version("1.0.0", "linux"),
+                version("1.1.0", "darwin"),
+                version("2.0.0-rc.1", "windows"),
+            ],
+            details,
+        );
+
+        assert_eq!(metadata.latest_version.as_deref(), Some("1.1.0"));
+        assert_eq!(
+            metadata.published_at.as_deref(),
+            Some("2024-02-01T00:00:00Z")
+        );
+        assert_eq!(metadata.platforms[0].os, "darwin");
+        assert_eq!(metadata.description.as_deref(), Some("Example provider"));
+    }
+
+    #[test]
+    fn metadata_falls_back_to_the_newest_prerelease() {
+        let metadata = provider_metadata(
+            "acme".parse().unwrap(),
+            "foo".parse().unwrap(),
+            vec![
+                version("1.0.0-beta.1", "linux"),
+                version("1.0.0-rc.1", "linux"),
+            ],
+            ProviderDetails::default(),
+        );
+
+        assert_eq!(metadata.latest_version.as_deref(), Some("1.0.0-rc.1"));
+        assert_eq!(metadata.published_at, None);
+    }
+}
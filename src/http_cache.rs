@@ -0,0 +1,140 @@
+use axum::http::{HeaderMap, HeaderValue, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+use serde_derive::{Deserialize, Serialize as SerializeDerive};
+use sha2::{Digest, Sha256};
+
+/// `Cache-Control` values sent with registry responses, so CDNs and proxies can cache them.
+#[derive(Deserialize, SerializeDerive, PartialEq, Clone, Debug)]
+pub struct HttpCacheConfig {
+    #[serde(default = "default_cache_control")]
+    pub versions: String,
+    #[serde(default = "default_cache_control")]
+    pub download: String,
+}
+
+/// Lets shared caches store responses, but only reuse them after revalidating the `ETag`.
+fn default_cache_control() -> String {
+    "no-cache".to_string()
+}
+
+impl Default for HttpCacheConfig {
+    fn default() -> Self {
+        Self {
+            versions: default_cache_control(),
+            download: default_cache_control(),
+        }
+    }
+}
+
+/// Serializes `body` as JSON with a strong `ETag` derived from its content, answering
+/// `304 Not Modified` when the request's `If-None-Match` already names that `ETag`.
+pub fn conditional_json<T: Serialize>(
+    request_headers: &HeaderMap,
+    body: &T,
+    cache_control: &str,
+) -> Response {
+    let Ok(bytes) = serde_json::to_vec(body) else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    let etag = format!("\"{:x}\"", Sha256::digest(&bytes));
+
+    let mut response = if if_none_match(request_headers, &etag) {
+        StatusCode::NOT_MODIFIED.into_response()
+    } else {
+        (
+            [(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static("application/json"),
+            )],
+            bytes,
+        )
+            .into_response()
+    };
+
+    let headers = response.headers_mut();
+    if let Ok(etag) = HeaderValue::from_str(&etag) {
+        headers.insert(header::ETAG, etag);
+    }
+    if let Ok(cache_control) = HeaderValue::from_str(cache_control) {
+        headers.insert(header::CACHE_CONTROL, cache_control);
+    }
+
+    response
+}
+
+/// Weak comparison, as RFC 9110 requires for `If-None-Match`.
+fn if_none_match(request_headers: &HeaderMap, etag: &str) -> bool {
+    request_headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .any(|candidate| candidate == "*" || candidate.trim_start_matches("W/") == etag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Platform;
+
+    fn platform() -> Platform {
+        Platform {
+            os: "linux".to_string(),
+            arch: "amd64".to_string(),
+        }
+    }
+
+    fn etag_of(response: &Response) -> String {
+        response
+            .headers()
+            .get(header::ETAG)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn etag_is_stable_for_identical_content() {
+        let first = conditional_json(&HeaderMap::new(), &platform(), "no-cache");
+        let second = conditional_json(&HeaderMap::new(), &platform(), "no-cache");
+
+        assert_eq!(first.status(), StatusCode::OK);
+        assert_eq!(etag_of(&first), etag_of(&second));
+    }
+
+    #[test]
+    fn matching_if_none_match_returns_not_modified() {
+        let etag = etag_of(&conditional_json(
+            &HeaderMap::new(),
+            &platform(),
+            "no-cache",
+        ));
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::IF_NONE_MATCH,
+            HeaderValue::from_str(&format!("\"other\", W/{etag}")).unwrap(),
+        );
+
+        let response = conditional_json(&headers, &platform(), "public, max-age=60");
+
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(etag_of(&response), etag);
+        assert_eq!(
+            response.headers().get(header::CACHE_CONTROL).unwrap(),
+            "public, max-age=60"
+        );
+    }
+
+    #[test]
+    fn stale_if_none_match_returns_body() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::IF_NONE_MATCH, HeaderValue::from_static("\"old\""));
+
+        let response = conditional_json(&headers, &platform(), "no-cache");
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
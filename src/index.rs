@@ -0,0 +1,705 @@
+use rusqlite::{Connection, OptionalExtension, params};
+use serde_derive::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+use tracing::{info, warn};
+
+use crate::package::{PackageCheckConfig, PackageVerifier};
+use crate::params::{Arch, Namespace, Os, ProviderType, Version};
+use crate::providers::{Backend, ProviderBackendError};
+use crate::types::{IndexedProvider, Package, Platform, VersionInfo};
+
+/// Persistent record of every provider, version and package the registry has seen.
+#[derive(Deserialize, Serialize, PartialEq, Clone, Debug)]
+pub struct IndexConfig {
+    pub path: PathBuf,
+    #[serde(default = "default_sync_interval_secs")]
+    pub sync_interval_secs: u64,
+    /// Providers the sync job copies from the configured backend into the index, in addition to
+    /// those the backend lists.
+    #[serde(default)]
+    pub providers: Vec<ProviderRef>,
+    /// Downloads and checks each package the first time it is synced, leaving out platforms
+    /// whose package fails the checks.
+    #[serde(default)]
+    pub verify_packages: Option<PackageCheckConfig>,
+}
+
+fn default_sync_interval_secs() -> u64 {
+    300
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Eq, Hash, Clone, Debug)]
+pub struct ProviderRef {
+    pub namespace: Namespace,
+    #[serde(rename = "type")]
+    pub provider_type: ProviderType,
+}
+
+#[derive(Error, Debug)]
+pub enum IndexError {
+    #[error("index database error: {0}")]
+    Database(#[from] rusqlite::Error),
+    #[error("backend error: {0}")]
+    Backend(#[from] ProviderBackendError),
+}
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS providers (
+    namespace TEXT NOT NULL,
+    type TEXT NOT NULL,
+    synced_at INTEGER NOT NULL,
+    PRIMARY KEY (namespace, type)
+);
+CREATE TABLE IF NOT EXISTS versions (
+    namespace TEXT NOT NULL,
+    type TEXT NOT NULL,
+    version TEXT NOT NULL,
+    protocols TEXT NOT NULL,
+    PRIMARY KEY (namespace, type, version),
+    FOREIGN KEY (namespace, type) REFERENCES providers ON DELETE CASCADE
+);
+CREATE TABLE IF NOT EXISTS platforms (
+    namespace TEXT NOT NULL,
+    type TEXT NOT NULL,
+    version TEXT NOT NULL,
+    os TEXT NOT NULL,
+    arch TEXT NOT NULL,
+    package TEXT,
+    shasum TEXT,
+    PRIMARY KEY (namespace, type, version, os, arch),
+    FOREIGN KEY (namespace, type, version) REFERENCES versions ON DELETE CASCADE
+);
+CREATE INDEX IF NOT EXISTS platforms_shasum ON platforms (shasum);
+CREATE TABLE IF NOT EXISTS signing_keys (
+    namespace TEXT NOT NULL,
+    type TEXT NOT NULL,
+    version TEXT NOT NULL,
+    key_id TEXT NOT NULL,
+    ascii_armor TEXT NOT NULL,
+    PRIMARY KEY (namespace, type, version, key_id),
+    FOREIGN KEY (namespace, type, version) REFERENCES versions ON DELETE CASCADE
+);
+";
+
+/// SQLite-backed metadata index. Package details are stored as the JSON download response, with
+/// the shasum and signing keys broken out so they can be queried across providers.
+pub struct MetadataIndex {
+    connection: Mutex<Connection>,
+}
+
+impl MetadataIndex {
+    pub fn open(path: &std::path::Path) -> Result<Self, IndexError> {
+        let connection = Connection::open(path)?;
+        connection.pragma_update(None, "foreign_keys", true)?;
+        connection.execute_batch(SCHEMA)?;
+
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    #[cfg(test)]
+    pub fn in_memory() -> Result<Self, IndexError> {
+        Self::open(std::path::Path::new(":memory:"))
+    }
+
+    fn connection(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.connection
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Replaces everything known about a provider in one transaction.
+    pub fn replace_provider(
+        &self,
+        namespace: &str,
+        provider_type: &str,
+        versions: &[VersionInfo],
+        packages: &[(String, Package)],
+    ) -> Result<(), IndexError> {
+        let mut connection = self.connection();
+        let tx = connection.transaction()?;
+
+        tx.execute(
+            "DELETE FROM providers WHERE namespace = ?1 AND type = ?2",
+            params![namespace, provider_type],
+        )?;
+        tx.execute(
+            "INSERT INTO providers (namespace, type, synced_at) VALUES (?1, ?2, ?3)",
+            params![namespace, provider_type, unix_now()],
+        )?;
+
+        // Two releases can read as the same version, e.g. tags `v1.0.0` and `1.0.0` under a custom
+        // tag pattern; the first one listed is kept.
+        for version in versions {
+            tx.execute(
+                "INSERT OR IGNORE INTO versions (namespace, type, version, protocols) VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    namespace,
+                    provider_type,
+                    version.version,
+                    version.protocols.join(",")
+                ],
+            )?;
+            for platform in &version.platforms {
+                tx.execute(
+                    "INSERT OR IGNORE INTO platforms (namespace, type, version, os, arch)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![
+                        namespace,
+                        provider_type,
+                        version.version,
+                        platform.os,
+                        platform.arch
+                    ],
+                )?;
+            }
+        }
+
+        for (version, package) in packages {
+            tx.execute(
+                "UPDATE platforms SET package = ?1, shasum = ?2
+                 WHERE namespace = ?3 AND type = ?4 AND version = ?5 AND os = ?6 AND arch = ?7",
+                params![
+                    serde_json::to_string(package).ok(),
+                    package.shasum,
+                    namespace,
+                    provider_type,
+                    version,
+                    package.os,
+                    package.arch
+                ],
+            )?;
+            for key in &package.signing_keys.gpg_public_keys {
+                tx.execute(
+                    "INSERT OR REPLACE INTO signing_keys (namespace, type, version, key_id, ascii_armor)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![
+                        namespace,
+                        provider_type,
+                        version,
+                        key.key_id,
+                        key.ascii_armor
+                    ],
+                )?;
+            }
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Versions of an indexed provider, or `None` if the provider has never been synced.
+    pub fn versions(
+        &self,
+        namespace: &str,
+        provider_type: &str,
+    ) -> Result<Option<Vec<VersionInfo>>, IndexError> {
+        let connection = self.connection();
+
+        let known: Option<i64> = connection
+            .query_row(
+                "SELECT 1 FROM providers WHERE namespace = ?1 AND type = ?2",
+                params![namespace, provider_type],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if known.is_none() {
+            return Ok(None);
+        }
+
+        let mut statement = connection.prepare(
+            "SELECT version, protocols FROM versions WHERE namespace = ?1 AND type = ?2
+             ORDER BY rowid",
+        )?;
+        let mut platforms = connection.prepare(
+            "SELECT os, arch FROM platforms
+             WHERE namespace = ?1 AND type = ?2 AND version = ?3 ORDER BY rowid",
+        )?;
+
+        let rows = statement
+            .query_map(params![namespace, provider_type], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut versions = Vec::with_capacity(rows.len());
+        for (version, protocols) in rows {
+            let platforms = platforms
+                .query_map(params![namespace, provider_type, version], |row| {
+                    Ok(Platform {
+                        os: row.get(0)?,
+                        arch: row.get(1)?,
+                    })
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            versions.push(VersionInfo {
+                version,
+                protocols: protocols.split(',').map(str::to_string).collect(),
+                platforms,
+                deprecation: None,
+            });
+        }
+
+        Ok(Some(versions))
+    }
+
+    /// The stored download details for a package, if the sync job managed to fetch them.
+    pub fn package(
+        &self,
+        namespace: &str,
+        provider_type: &str,
+        version: &str,
+        os: &str,
+        arch: &str,
+    ) -> Result<Option<Package>, IndexError> {
+        let package: Option<Option<String>> = self
+            .connection()
+            .query_row(
+                "SELECT package FROM platforms
+                 WHERE namespace = ?1 AND type = ?2 AND version = ?3 AND os = ?4 AND arch = ?5",
+                params![namespace, provider_type, version, os, arch],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        Ok(package
+            .flatten()
+            .and_then(|json| serde_json::from_str(&json).ok()))
+    }
+
+    /// Every indexed provider with its versions, across all namespaces.
+    pub fn providers(&self) -> Result<Vec<IndexedProvider>, IndexError> {
+        let connection = self.connection();
+        let mut statement = connection.prepare(
+            "SELECT p.namespace, p.type, p.synced_at, group_concat(v.version, ',' ORDER BY v.rowid)
+             FROM providers p LEFT JOIN versions v ON v.namespace = p.namespace AND v.type = p.type
+             GROUP BY p.namespace, p.type ORDER BY p.namespace, p.type",
+        )?;
+
+        let providers = statement
+            .query_map([], |row| {
+                let versions: Option<String> = row.get(3)?;
+                Ok(IndexedProvider {
+                    namespace: row.get(0)?,
+                    provider_type: row.get(1)?,
+                    synced_at: row.get(2)?,
+                    versions: versions
+                        .map(|v| v.split(',').map(str::to_string).collect())
+                        .unwrap_or_default(),
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(providers)
+    }
+}
+
+fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| i64::try_from(d.as_secs()).unwrap_or(i64::MAX))
+}
+
+/// Copies one provider from the backend into the index. Published versions do not change, so
+/// only packages of versions new to the index are fetched; those that cannot be fetched are
+/// recorded without download details and looked up from the backend on demand. With a
+/// `verifier`, new packages are checked first.
+pub fn sync_provider(
+    backend: &dyn Backend,
+    index: &MetadataIndex,
+    provider: &ProviderRef,
+    verifier: Option<&PackageVerifier>,
+) -> Result<(), IndexError> {
+    let mut versions = backend
+        .list_provider_versions(provider.namespace.clone(), provider.provider_type.clone())?;
+    let indexed = index
+        .versions(&provider.namespace, &provider.provider_type)?
+        .unwrap_or_default();
+
+    let mut packages = Vec::new();
+    for version in &mut versions {
+        if let Some(known) = indexed
+            .iter()
+            .find(|known| known.version == version.version)
+        {
+            version.platforms.clone_from(&known.platforms);
+            for platform in &version.platforms {
+                if let Some(package) = index.package(
+                    &provider.namespace,
+                    &provider.provider_type,
+                    &version.version,
+                    &platform.os,
+                    &platform.arch,
+                )? {
+                    packages.push((version.version.clone(), package));
+                }
+            }
+            continue;
+        }
+
+        let mut rejected = Vec::new();
+        for platform in &version.platforms {
+            let lookup = Version::new(version.version.as_str()).and_then(|version| {
+                Ok((
+                    version,
+                    Os::new(platform.os.as_str())?,
+                    Arch::new(platform.arch.as_str())?,
+                ))
+            });
+            let (typed_version, os, arch) = match lookup {
+                Ok(lookup) => lookup,
+                Err(error) => {
+                    warn!(
+                        namespace = %provider.namespace,
+                        provider_type = %provider.provider_type,
+                        "Leaving package out of the index: {error}"
+                    );
+                    rejected.push((platform.os.clone(), platform.arch.clone()));
+                    continue;
+                }
+            };
+            match backend.find_provider_package(
+                provider.namespace.clone(),
+                provider.provider_type.clone(),
+                typed_version,
+                os,
+                arch,
+            ) {
+                Ok(package) => {
+                    if let Some(verifier) = verifier
+                        && let Err(error) =
+                            verifier.verify(&provider.provider_type, &version.version, &package)
+                    {
+                        warn!(
+                            namespace = %provider.namespace,
+                            provider_type = %provider.provider_type,
+                            version = %version.version,
+                            os = %platform.os,
+                            arch = %platform.arch,
+                            "Leaving package out of the index: `{}` {error}",
+                            package.filename
+                        );
+                        rejected.push((platform.os.clone(), platform.arch.clone()));
+                        continue;
+                    }
+                    packages.push((version.version.clone(), package));
+                }
+                Err(error) => warn!(
+                    namespace = %provider.namespace,
+                    provider_type = %provider.provider_type,
+                    version = %version.version,
+                    os = %platform.os,
+                    arch = %platform.arch,
+                    "Failed to fetch package during index sync: {error}"
+                ),
+            }
+        }
+        version.platforms.retain(|p| {
+            !rejected
+                .iter()
+                .any(|(os, arch)| *os == p.os && *arch == p.arch)
+        });
+    }
+
+    index.replace_provider(
+        &provider.namespace,
+        &provider.provider_type,
+        &versions,
+        &packages,
+    )
+}
+
+/// The configured providers followed by any others the backend lists.
+fn providers_to_sync(backend: &dyn Backend, configured: &[ProviderRef]) -> Vec<ProviderRef> {
+    let mut providers = configured.to_vec();
+    match backend.list_providers() {
+        Ok(listed) => {
+            for provider in listed {
+                if !providers.contains(&provider) {
+                    providers.push(provider);
+                }
+            }
+        }
+        Err(error) => warn!("Cannot list backend providers, syncing configured ones only: {error}"),
+    }
+    providers
+}
+
+/// Periodically syncs every configured or backend-listed provider into the index.
+pub fn spawn_sync(
+    backend: Arc<dyn Backend>,
+    index: Arc<MetadataIndex>,
+    config: &IndexConfig,
+) -> tokio::task::JoinHandle<()> {
+    let configured = config.providers.clone();
+    let checks = config.verify_packages.clone();
+    let interval = Duration::from_secs(config.sync_interval_secs.max(1));
+
+    tokio::spawn(async move {
+        // The verifier's blocking HTTP client cannot be created on a runtime thread.
+        let verifier = match tokio::task::spawn_blocking(move || {
+            checks.map(|checks| Arc::new(PackageVerifier::new(checks)))
+        })
+        .await
+        {
+            Ok(verifier) => verifier,
+            Err(error) => {
+                warn!("Cannot create package verifier, index sync disabled: {error}");
+                return;
+            }
+        };
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+
+            let (list_backend, list_configured) = (backend.clone(), configured.clone());
+            let providers = tokio::task::spawn_blocking(move || {
+                providers_to_sync(list_backend.as_ref(), &list_configured)
+            })
+            .await
+            .unwrap_or_else(|error| {
+                warn!("Listing backend providers panicked: {error}");
+                configured.clone()
+            });
+
+            for provider in &providers {
+                let (backend, index, task_provider, verifier) = (
+                    backend.clone(),
+                    index.clone(),
+                    provider.clone(),
+                    verifier.clone(),
+                );
+                let result = tokio::task::spawn_blocking(move || {
+                    sync_provider(
+                        backend.as_ref(),
+                        &index,
+                        &task_provider,
+                        verifier.as_deref(),
+                    )
+                })
+                .await;
+
+                match result {
+                    Ok(Ok(())) => info!(
+                        namespace = %provider.namespace,
+                        provider_type = %provider.provider_type,
+                        "Synced provider into index"
+                    ),
+                    Ok(Err(error)) => warn!(
+                        namespace = %provider.namespace,
+                        provider_type = %provider.provider_type,
+                        "Index sync failed: {error}"
+                    ),
+                    Err(error) => warn!(
+                        namespace = %provider.namespace,
+                        provider_type = %provider.provider_type,
+                        "Index sync panicked: {error}"
+                    ),
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::FakeBackend;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Serves `FakeBackend`'s releases, counting package lookups, and lists one provider.
+    #[derive(Default)]
+    struct CountingBackend {
+        lookups: AtomicUsize,
+    }
+
+    impl Backend for CountingBackend {
+        fn name(&self) -> &'static str {
+            "counting"
+        }
+
+        fn health(&self) -> crate::providers::Result<()> {
+            Ok(())
+        }
+
+        fn list_provider_versions(
+            &self,
+            namespace: Namespace,
+            provider_type: ProviderType,
+        ) -> crate::providers::Result<Vec<VersionInfo>> {
+            FakeBackend.list_provider_versions(namespace, provider_type)
+        }
+
+        fn find_provider_package(
+            &self,
+            namespace: Namespace,
+            provider_type: ProviderType,
+            version: Version,
+            os: Os,
+            arch: Arch,
+        ) -> crate::providers::Result<Package> {
+            self.lookups.fetch_add(1, Ordering::SeqCst);
+            FakeBackend.find_provider_package(namespace, provider_type, version, os, arch)
+        }
+
+        fn list_providers(&self) -> crate::providers::Result<Vec<ProviderRef>> {
+            Ok(vec![
+                hashicorp_aws(),
+                ProviderRef {
+                    namespace: "acme".parse().unwrap(),
+                    provider_type: "foo".parse().unwrap(),
+                },
+            ])
+        }
+    }
+
+    fn hashicorp_aws() -> ProviderRef {
+        ProviderRef {
+            namespace: "hashicorp".parse().unwrap(),
+            provider_type: "aws".parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn sync_records_versions_packages_and_keys() {
+        let index = MetadataIndex::in_memory().unwrap();
+
+        sync_provider(&FakeBackend, &index, &hashicorp_aws(), None).unwrap();
+
+        let versions = index.versions("hashicorp", "aws").unwrap().unwrap();
+        assert_eq!(versions.len(), 2);
+        assert_eq!(versions[0].version, "1.0.0");
+        assert_eq!(versions[0].platforms.len(), 5);
+
+        let package = index
+            .package("hashicorp", "aws", "1.0.0", "darwin", "arm64")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            package.filename,
+            "terraform-provider-aws_1.0.0_darwin_arm64.zip"
+        );
+
+        assert_eq!(
+            package.signing_keys.gpg_public_keys[0].key_id,
+            "0123456789ABCDEF"
+        );
+    }
+
+    #[test]
+    fn resync_replaces_previous_contents() {
+        let index = MetadataIndex::in_memory().unwrap();
+
+        sync_provider(&FakeBackend, &index, &hashicorp_aws(), None).unwrap();
+        sync_provider(&FakeBackend, &index, &hashicorp_aws(), None).unwrap();
+
+        assert_eq!(
+            index.versions("hashicorp", "aws").unwrap().unwrap().len(),
+            2
+        );
+    }
+
+    #[test]
+    fn resync_fetches_only_new_versions() {
+        let index = MetadataIndex::in_memory().unwrap();
+        let versions = FakeBackend
+            .list_provider_versions("hashicorp".parse().unwrap(), "aws".parse().unwrap())
+            .unwrap();
+        index
+            .replace_provider("hashicorp", "aws", &versions[..1], &[])
+            .unwrap();
+        let backend = CountingBackend::default();
+
+        sync_provider(&backend, &index, &hashicorp_aws(), None).unwrap();
+        assert_eq!(
+            backend.lookups.load(Ordering::SeqCst),
+            versions[1].platforms.len()
+        );
+        assert!(
+            index
+                .package("hashicorp", "aws", "0.9.0", "linux", "amd64")
+                .unwrap()
+                .is_some()
+        );
+
+        sync_provider(&backend, &index, &hashicorp_aws(), None).unwrap();
+        assert_eq!(
+            backend.lookups.load(Ordering::SeqCst),
+            versions[1].platforms.len()
+        );
+        assert!(
+            index
+                .package("hashicorp", "aws", "0.9.0", "linux", "amd64")
+                .unwrap()
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn backend_listed_providers_are_synced_after_configured_ones() {
+        let configured = vec![
+            ProviderRef {
+                namespace: "other".parse().unwrap(),
+                provider_type: "bar".parse().unwrap(),
+            },
+            hashicorp_aws(),
+        ];
+
+        let providers = providers_to_sync(&CountingBackend::default(), &configured);
+
+        assert_eq!(providers.len(), 3);
+        assert_eq!(providers[..2], configured[..]);
+        assert_eq!(providers[2].namespace.as_str(), "acme");
+    }
+
+    #[test]
+    fn duplicate_versions_are_indexed_once() {
+        let index = MetadataIndex::in_memory().unwrap();
+        let mut versions = FakeBackend
+            .list_provider_versions("hashicorp".parse().unwrap(), "aws".parse().unwrap())
+            .unwrap();
+        versions.push(versions[0].clone());
+
+        index
+            .replace_provider("hashicorp", "aws", &versions, &[])
+            .unwrap();
+
+        assert_eq!(
+            index.versions("hashicorp", "aws").unwrap().unwrap().len(),
+            2
+        );
+    }
+
+    #[test]
+    fn unknown_provider_is_none() {
+        let index = MetadataIndex::in_memory().unwrap();
+
+        assert!(index.versions("hashicorp", "aws").unwrap().is_none());
+    }
+
+    #[test]
+    fn providers_lists_everything_indexed() {
+        let index = MetadataIndex::in_memory().unwrap();
+        sync_provider(&FakeBackend, &index, &hashicorp_aws(), None).unwrap();
+        sync_provider(
+            &FakeBackend,
+            &index,
+            &ProviderRef {
+                namespace: "acme".parse().unwrap(),
+                provider_type: "foo".parse().unwrap(),
+            },
+            None,
+        )
+        .unwrap();
+
+        let providers = index.providers().unwrap();
+
+        assert_eq!(providers.len(), 2);
+        assert_eq!(providers[0].namespace, "acme");
+        assert_eq!(providers[1].versions, vec!["1.0.0", "0.9.0"]);
+    }
+}
@@ -0,0 +1,96 @@
+use semver::Version;
+use serde_derive::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+use crate::types::VersionInfo;
+
+/// How version listings are presented, whichever backend they come from.
+#[derive(Deserialize, Serialize, PartialEq, Clone, Debug, Default)]
+pub struct ListingConfig {
+    /// Leaves prerelease versions out of listings. They can still be downloaded by exact version.
+    #[serde(default)]
+    pub hide_prereleases: bool,
+}
+
+/// Sorts versions by semver precedence, oldest first, and keeps one entry per version. Of
+/// versions differing only in build metadata, such as `1.0.0` and `1.0.0+build`, the one without
+/// metadata is kept. Versions that are not valid semver go last, in the order given.
+pub fn normalize(versions: Vec<VersionInfo>, config: &ListingConfig) -> Vec<VersionInfo> {
+    let mut parsed: Vec<(Option<Version>, VersionInfo)> = versions
+        .into_iter()
+        .map(|version| (Version::parse(&version.version).ok(), version))
+        .collect();
+    if config.hide_prereleases {
+        parsed.retain(|(semver, _)| semver.as_ref().is_none_or(|semver| semver.pre.is_empty()));
+    }
+
+    parsed.sort_by(|(a, _), (b, _)| match (a, b) {
+        (Some(a), Some(b)) => a
+            .cmp_precedence(b)
+            .then_with(|| b.build.is_empty().cmp(&a.build.is_empty())),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    });
+    parsed.dedup_by(|(a, later), (b, earlier)| match (a, b) {
+        (Some(a), Some(b)) => a.cmp_precedence(b) == Ordering::Equal,
+        (None, None) => later.version == earlier.version,
+        _ => false,
+    });
+
+    parsed.into_iter().map(|(_, version)| version).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn versions(names: &[&str]) -> Vec<VersionInfo> {
+        names
+            .iter()
+            .map(|name| VersionInfo {
+                version: (*name).to_string(),
+                protocols: vec!["5.0".to_string()],
+                platforms: Vec::new(),
+                deprecation: None,
+            })
+            .collect()
+    }
+
+    fn names(versions: &[VersionInfo]) -> Vec<&str> {
+        versions.iter().map(|v| v.version.as_str()).collect()
+    }
+
+    #[test]
+    fn sorts_by_precedence_and_drops_duplicates() {
+        let listed = normalize(
+            versions(&[
+                "1.10.0",
+                "nightly",
+                "1.0.0+build",
+                "1.2.0",
+                "1.0.0",
+                "1.2.0-rc.1",
+                "1.2.0",
+            ]),
+            &ListingConfig::default(),
+        );
+
+        assert_eq!(
+            names(&listed),
+            vec!["1.0.0", "1.2.0-rc.1", "1.2.0", "1.10.0", "nightly"]
+        );
+    }
+
+    #[test]
+    fn hides_prereleases_when_configured() {
+        let listed = normalize(
+            versions(&["2.0.0-beta.1", "1.0.0", "2.0.0-rc.1+build"]),
+            &ListingConfig {
+                hide_prereleases: true,
+            },
+        );
+
+        assert_eq!(names(&listed), vec!["1.0.0"]);
+    }
+}
@@ -1,34 +1,158 @@
+mod auth;
+mod cli;
 mod config;
+mod constraint;
+mod discovery;
+mod http_cache;
+mod index;
+mod listing;
+mod metrics;
+mod overlay;
+mod package;
+mod params;
 mod providers;
+mod publish;
 mod routes;
+mod search;
+mod shutdown;
+mod signing;
+mod telemetry;
+mod tls;
 mod types;
 
-use tracing::info;
+use axum_server::Handle;
+use index::MetadataIndex;
+use metrics::Metrics;
+use overlay::VersionOverlay;
+use providers::{
+    Backend, CachingBackend, CoalescingBackend, IndexedBackend, InstrumentedBackend, OverlayBackend,
+};
+use shutdown::Shutdown;
+use std::sync::Arc;
+use telemetry::Telemetry;
+use tracing::{info, warn};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    // Initialize tracing
-    tracing_subscriber::fmt()
-        .with_target(false)
-        .compact()
-        .init();
-
+    let command = cli::Command::parse(std::env::args().skip(1))?;
     let config = config::AppConfig::load("config.yaml")?;
-    let providers = config.providers_backend()?;
-    let listener = tokio::net::TcpListener::bind(config.bind_address).await?;
+
+    if let cli::Command::Diagnose {
+        namespace,
+        provider_type,
+    } = command
+    {
+        return tokio::task::spawn_blocking(move || {
+            cli::diagnose_configured(&config, namespace, provider_type)
+        })
+        .await?
+        .map_err(Into::into);
+    }
+
+    // Initialize tracing
+    let telemetry = Telemetry::init(&config.telemetry)?;
+
+    // Creating a blocking HTTP client on a runtime thread panics in debug builds.
+    let (backend, publisher) = tokio::task::block_in_place(|| {
+        Ok::<_, providers::ProviderBackendError>((
+            config.providers_backend()?,
+            config.writable_backend()?,
+        ))
+    })?;
+
+    let metrics = Metrics::new()?;
+    let mut providers: Arc<dyn Backend> = Arc::new(CoalescingBackend::new(Arc::new(
+        InstrumentedBackend::new(backend, metrics.clone()),
+    )));
+    let mut index = None;
+    if let Some(index_config) = &config.index {
+        let metadata_index = Arc::new(MetadataIndex::open(&index_config.path)?);
+        index::spawn_sync(providers.clone(), metadata_index.clone(), index_config);
+        providers = Arc::new(IndexedBackend::new(providers, metadata_index.clone()));
+        index = Some(metadata_index);
+    }
+    if let Some(cache) = &config.cache {
+        providers = Arc::new(CachingBackend::new(providers, cache, metrics.clone()));
+    }
+    // Outside the cache, so status changes apply immediately.
+    let mut overlay = None;
+    if let Some(overlay_config) = &config.overlay {
+        let version_overlay = Arc::new(VersionOverlay::open(overlay_config)?);
+        providers = Arc::new(OverlayBackend::new(providers, version_overlay.clone()));
+        overlay = Some(version_overlay);
+    }
 
     // Build the application
-    let app = routes::app(providers);
-    info!("Server listening on {}", config.bind_address);
-    axum::serve(listener, app).await?;
+    let shutdown = Shutdown::default();
+    let mut state = routes::AppState::new(providers, metrics)
+        .with_shutdown(shutdown.clone())
+        .with_http_cache(config.http_cache.clone())
+        .with_listing(config.listing.clone())
+        .with_auth(config.auth.clone())
+        .with_platforms(config.platforms.clone());
+    if let Some(index) = index {
+        state = state.with_index(index);
+    }
+    if let Some(overlay) = overlay {
+        state = state.with_overlay(overlay);
+    }
+    if let Some(publisher) = publisher {
+        state = state.with_publisher(publisher, config.publish.clone());
+    }
+    if let Some(signing) = &config.publish.signing {
+        state = state.with_signer(Arc::new(signing::RegistrySigner::load(signing)?));
+    }
+    if let config::ProvidersBackend::Filesystem(filesystem) = &config.providers_backend {
+        state = state.with_files(filesystem.root.clone());
+    }
+    let app = routes::app(state);
+    let signal = shutdown.clone().wait_for_signal(config.shutdown.clone());
+    let drain_timeout = config.shutdown.drain_timeout();
+
+    if let Some(tls) = config.tls {
+        let rustls_config = tls.rustls_config()?;
+        tls.spawn_reloader(rustls_config.clone());
+
+        let handle = Handle::new();
+        tokio::spawn({
+            let handle = handle.clone();
+            async move {
+                signal.await;
+                handle.graceful_shutdown(Some(drain_timeout));
+            }
+        });
+
+        info!("Server listening on {} (TLS)", config.bind_address);
+        axum_server::bind_rustls(config.bind_address, rustls_config)
+            .handle(handle)
+            .serve(app.into_make_service())
+            .await?;
+    } else {
+        let listener = tokio::net::TcpListener::bind(config.bind_address).await?;
+        info!("Server listening on {}", config.bind_address);
+
+        let server = axum::serve(listener, app).with_graceful_shutdown(signal);
+        let deadline = async {
+            shutdown.draining().await;
+            tokio::time::sleep(config.shutdown.shutdown_delay() + drain_timeout).await;
+        };
+
+        tokio::select! {
+            result = server => result?,
+            () = deadline => warn!("Drain timeout elapsed, closing remaining connections"),
+        }
+    }
 
+    telemetry.shutdown();
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::metrics::Metrics;
     use crate::providers::FakeBackend;
     use crate::routes;
+    use crate::routes::AppState;
     use axum::body::Body;
     use axum::http::{Request, StatusCode};
     use std::sync::Arc;
@@ -37,7 +161,7 @@ mod tests {
     #[tokio::test]
     async fn test_service_discovery_returns_ok() {
         let providers = Arc::new(FakeBackend);
-        let app = routes::app(providers);
+        let app = routes::app(AppState::new(providers, Metrics::new().unwrap()));
 
         let response = app
             .oneshot(
@@ -55,7 +179,7 @@ mod tests {
     #[tokio::test]
     async fn test_health_check_returns_ok() {
         let providers = Arc::new(FakeBackend);
-        let app = routes::app(providers);
+        let app = routes::app(AppState::new(providers, Metrics::new().unwrap()));
 
         let response = app
             .oneshot(
@@ -73,7 +197,7 @@ mod tests {
     #[tokio::test]
     async fn test_list_versions_returns_ok() {
         let providers = Arc::new(FakeBackend);
-        let app = routes::app(providers);
+        let app = routes::app(AppState::new(providers, Metrics::new().unwrap()));
 
         let response = app
             .oneshot(
@@ -91,7 +215,7 @@ mod tests {
     #[tokio::test]
     async fn test_download_endpoint_returns_ok() {
         let providers = Arc::new(FakeBackend);
-        let app = routes::app(providers);
+        let app = routes::app(AppState::new(providers, Metrics::new().unwrap()));
 
         let response = app
             .oneshot(
@@ -0,0 +1,196 @@
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::{StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry, TEXT_FORMAT, TextEncoder,
+};
+use std::time::{Duration, Instant};
+
+use crate::providers::ProviderBackendError;
+
+/// Prometheus collectors for the registry, exposed on `/metrics`.
+#[derive(Clone)]
+pub struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_request_duration: HistogramVec,
+    backend_calls: IntCounterVec,
+    backend_call_duration: HistogramVec,
+    downloads: IntCounterVec,
+    cache_lookups: IntCounterVec,
+}
+
+impl Metrics {
+    pub fn new() -> prometheus::Result<Self> {
+        let registry = Registry::new_custom(Some("terraform_registry".to_string()), None)?;
+
+        let http_requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP requests by route and status"),
+            &["route", "method", "status"],
+        )?;
+        let http_request_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "HTTP request latency by route and status",
+            ),
+            &["route", "method", "status"],
+        )?;
+        let backend_calls = IntCounterVec::new(
+            Opts::new(
+                "backend_calls_total",
+                "Backend calls by method and result, where any result other than `ok` is an error",
+            ),
+            &["backend", "method", "result"],
+        )?;
+        let backend_call_duration = HistogramVec::new(
+            HistogramOpts::new("backend_call_duration_seconds", "Backend call latency"),
+            &["backend", "method"],
+        )?;
+        let downloads = IntCounterVec::new(
+            Opts::new("downloads_total", "Provider package downloads served"),
+            &["namespace", "type", "version", "os", "arch"],
+        )?;
+
+        registry.register(Box::new(http_requests.clone()))?;
+        registry.register(Box::new(http_request_duration.clone()))?;
+        registry.register(Box::new(backend_calls.clone()))?;
+        registry.register(Box::new(backend_call_duration.clone()))?;
+        let cache_lookups = IntCounterVec::new(
+            Opts::new(
+                "cache_lookups_total",
+                "Backend cache lookups by cache and result (hit, negative_hit or miss)",
+            ),
+            &["cache", "result"],
+        )?;
+
+        registry.register(Box::new(downloads.clone()))?;
+        registry.register(Box::new(cache_lookups.clone()))?;
+
+        Ok(Self {
+            registry,
+            http_requests,
+            http_request_duration,
+            backend_calls,
+            backend_call_duration,
+            downloads,
+            cache_lookups,
+        })
+    }
+
+    pub fn observe_request(
+        &self,
+        route: &str,
+        method: &str,
+        status: StatusCode,
+        elapsed: Duration,
+    ) {
+        let labels = [route, method, status.as_str()];
+        self.http_requests.with_label_values(&labels).inc();
+        self.http_request_duration
+            .with_label_values(&labels)
+            .observe(elapsed.as_secs_f64());
+    }
+
+    pub fn observe_backend_call<T>(
+        &self,
+        backend: &str,
+        method: &str,
+        result: &std::result::Result<T, ProviderBackendError>,
+        elapsed: Duration,
+    ) {
+        let outcome = match result {
+            Ok(_) => "ok",
+            Err(error) => error.kind(),
+        };
+        self.backend_calls
+            .with_label_values(&[backend, method, outcome])
+            .inc();
+        self.backend_call_duration
+            .with_label_values(&[backend, method])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    pub fn record_download(
+        &self,
+        namespace: &str,
+        provider_type: &str,
+        version: &str,
+        os: &str,
+        arch: &str,
+    ) {
+        self.downloads
+            .with_label_values(&[namespace, provider_type, version, os, arch])
+            .inc();
+    }
+
+    pub fn record_cache_lookup(&self, cache: &str, result: &str) {
+        self.cache_lookups.with_label_values(&[cache, result]).inc();
+    }
+
+    /// Encodes every registered metric in the Prometheus text format.
+    pub fn render(&self) -> prometheus::Result<String> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        String::from_utf8(buffer).map_err(|e| prometheus::Error::Msg(e.to_string()))
+    }
+}
+
+/// Middleware recording request counts and latency per matched route
+pub async fn track_requests(
+    State(metrics): State<Metrics>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or_else(|| "unmatched".to_string(), |path| path.as_str().to_string());
+    let method = request.method().to_string();
+    let started = Instant::now();
+
+    let response = next.run(request).await;
+
+    metrics.observe_request(&route, &method, response.status(), started.elapsed());
+    response
+}
+
+/// Metrics endpoint - Prometheus text exposition
+pub async fn metrics_handler(State(metrics): State<Metrics>) -> Response {
+    match metrics.render() {
+        Ok(body) => ([(header::CONTENT_TYPE, TEXT_FORMAT)], body).into_response(),
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_includes_recorded_values() {
+        let metrics = Metrics::new().unwrap();
+        metrics.observe_request("/health", "GET", StatusCode::OK, Duration::from_millis(5));
+        metrics.observe_backend_call::<()>(
+            "fake",
+            "list_provider_versions",
+            &Err(ProviderBackendError::NotFound),
+            Duration::from_millis(5),
+        );
+        metrics.record_download("hashicorp", "aws", "1.0.0", "linux", "amd64");
+
+        let body = metrics.render().unwrap();
+
+        assert!(body.contains(
+            r#"terraform_registry_http_requests_total{method="GET",route="/health",status="200"} 1"#
+        ));
+        assert!(body.contains(
+            r#"terraform_registry_backend_calls_total{backend="fake",method="list_provider_versions",result="not_found"} 1"#
+        ));
+        assert!(body.contains(
+            r#"terraform_registry_downloads_total{arch="amd64",namespace="hashicorp",os="linux",type="aws",version="1.0.0"} 1"#
+        ));
+    }
+}
@@ -0,0 +1,259 @@
+use axum::{
+    Json,
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, PoisonError, RwLock};
+use thiserror::Error;
+use tracing::info;
+
+use crate::params::{Namespace, ProviderType, ValidPath, Version};
+use crate::providers::ProviderBackendError;
+
+/// Statuses set through the admin API, layered over whatever the backend serves.
+#[derive(Deserialize, Serialize, PartialEq, Clone, Debug)]
+pub struct OverlayConfig {
+    /// JSON file the statuses are kept in; created on the first change.
+    pub path: PathBuf,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Eq, Clone, Debug)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum VersionStatus {
+    /// Left out of version listings, but still downloadable by explicit version.
+    Yanked,
+    /// Listed, with the message passed on to Terraform as a warning.
+    Deprecated { message: String },
+}
+
+#[derive(Error, Debug)]
+pub enum OverlayError {
+    #[error("cannot access overlay file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("overlay file is malformed: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+type VersionKey = (String, String, String);
+
+#[derive(Serialize, Deserialize)]
+struct StoredStatus {
+    namespace: String,
+    #[serde(rename = "type")]
+    provider_type: String,
+    version: String,
+    #[serde(flatten)]
+    status: VersionStatus,
+}
+
+/// Yanked and deprecated provider versions, held in memory and written through to a JSON file.
+pub struct VersionOverlay {
+    path: PathBuf,
+    statuses: RwLock<HashMap<VersionKey, VersionStatus>>,
+}
+
+impl VersionOverlay {
+    pub fn open(config: &OverlayConfig) -> Result<Self, OverlayError> {
+        let statuses = match std::fs::read(&config.path) {
+            Ok(contents) => serde_json::from_slice::<Vec<StoredStatus>>(&contents)?
+                .into_iter()
+                .map(|stored| {
+                    (
+                        (stored.namespace, stored.provider_type, stored.version),
+                        stored.status,
+                    )
+                })
+                .collect(),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(error) => return Err(error.into()),
+        };
+
+        Ok(Self {
+            path: config.path.clone(),
+            statuses: RwLock::new(statuses),
+        })
+    }
+
+    pub fn status(
+        &self,
+        namespace: &str,
+        provider_type: &str,
+        version: &str,
+    ) -> Option<VersionStatus> {
+        self.statuses
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&(
+                namespace.to_string(),
+                provider_type.to_string(),
+                version.to_string(),
+            ))
+            .cloned()
+    }
+
+    /// Sets or, with `None`, clears the status of a version. The file is rewritten before the
+    /// change becomes visible, so a failed write leaves the overlay as it was.
+    pub fn set_status(
+        &self,
+        namespace: &str,
+        provider_type: &str,
+        version: &str,
+        status: Option<VersionStatus>,
+    ) -> Result<(), OverlayError> {
+        let mut statuses = self
+            .statuses
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        let key = (
+            namespace.to_string(),
+            provider_type.to_string(),
+            version.to_string(),
+        );
+
+        let mut updated = statuses.clone();
+        match status {
+            Some(status) => updated.insert(key, status),
+            None => updated.remove(&key),
+        };
+        self.write(&updated)?;
+        *statuses = updated;
+        Ok(())
+    }
+
+    fn write(&self, statuses: &HashMap<VersionKey, VersionStatus>) -> Result<(), OverlayError> {
+        let mut stored: Vec<_> = statuses
+            .iter()
+            .map(
+                |((namespace, provider_type, version), status)| StoredStatus {
+                    namespace: namespace.clone(),
+                    provider_type: provider_type.clone(),
+                    version: version.clone(),
+                    status: status.clone(),
+                },
+            )
+            .collect();
+        stored.sort_by(|a, b| {
+            (&a.namespace, &a.provider_type, &a.version).cmp(&(
+                &b.namespace,
+                &b.provider_type,
+                &b.version,
+            ))
+        });
+
+        let partial = self.path.with_extension("partial");
+        std::fs::write(&partial, serde_json::to_vec_pretty(&stored)?)?;
+        std::fs::rename(&partial, &self.path)?;
+        Ok(())
+    }
+}
+
+/// Returns the status of a version, or 404 if it has none
+pub async fn get_status_handler(
+    State(overlay): State<Option<Arc<VersionOverlay>>>,
+    ValidPath((namespace, provider_type, version)): ValidPath<(Namespace, ProviderType, Version)>,
+) -> Response {
+    match overlay.and_then(|overlay| overlay.status(&namespace, &provider_type, &version)) {
+        Some(status) => Json(status).into_response(),
+        None => ProviderBackendError::NotFound.into_response(),
+    }
+}
+
+/// Marks a version as yanked or deprecated
+pub async fn put_status_handler(
+    State(overlay): State<Option<Arc<VersionOverlay>>>,
+    ValidPath((namespace, provider_type, version)): ValidPath<(Namespace, ProviderType, Version)>,
+    Json(status): Json<VersionStatus>,
+) -> Response {
+    update_status(overlay, namespace, provider_type, version, Some(status)).await
+}
+
+/// Clears the status of a version, restoring it as the backend serves it
+pub async fn delete_status_handler(
+    State(overlay): State<Option<Arc<VersionOverlay>>>,
+    ValidPath((namespace, provider_type, version)): ValidPath<(Namespace, ProviderType, Version)>,
+) -> Response {
+    update_status(overlay, namespace, provider_type, version, None).await
+}
+
+async fn update_status(
+    overlay: Option<Arc<VersionOverlay>>,
+    namespace: Namespace,
+    provider_type: ProviderType,
+    version: Version,
+    status: Option<VersionStatus>,
+) -> Response {
+    let Some(overlay) = overlay else {
+        return ProviderBackendError::NotFound.into_response();
+    };
+
+    let result = tokio::task::spawn_blocking(move || {
+        overlay.set_status(&namespace, &provider_type, &version, status.clone())?;
+        info!(%namespace, %provider_type, %version, ?status, "Version status changed");
+        Ok::<_, OverlayError>(status)
+    })
+    .await;
+
+    match result {
+        Ok(Ok(Some(status))) => Json(status).into_response(),
+        Ok(Ok(None)) => StatusCode::NO_CONTENT.into_response(),
+        Ok(Err(error)) => ProviderBackendError::storage(error).into_response(),
+        Err(error) => ProviderBackendError::storage(error).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn statuses_survive_reopening() {
+        let directory = tempfile::tempdir().unwrap();
+        let config = OverlayConfig {
+            path: directory.path().join("overlay.json"),
+        };
+
+        let overlay = VersionOverlay::open(&config).unwrap();
+        overlay
+            .set_status("acme", "foo", "1.0.0", Some(VersionStatus::Yanked))
+            .unwrap();
+        overlay
+            .set_status(
+                "acme",
+                "foo",
+                "1.1.0",
+                Some(VersionStatus::Deprecated {
+                    message: "use 2.x".to_string(),
+                }),
+            )
+            .unwrap();
+        overlay.set_status("acme", "foo", "1.0.0", None).unwrap();
+        let reopened = VersionOverlay::open(&config).unwrap();
+
+        assert_eq!(reopened.status("acme", "foo", "1.0.0"), None);
+        assert_eq!(
+            reopened.status("acme", "foo", "1.1.0"),
+            Some(VersionStatus::Deprecated {
+                message: "use 2.x".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn failed_writes_leave_statuses_unchanged() {
+        let overlay = VersionOverlay::open(&OverlayConfig {
+            path: "/nonexistent/overlay.json".into(),
+        })
+        .unwrap();
+
+        assert!(
+            overlay
+                .set_status("acme", "foo", "1.0.0", Some(VersionStatus::Yanked))
+                .is_err()
+        );
+        assert_eq!(overlay.status("acme", "foo", "1.0.0"), None);
+    }
+}
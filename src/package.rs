@@ -0,0 +1,504 @@
+use reqwest::blocking::Client;
+use serde_derive::{Deserialize, Serialize};
+use std::fmt;
+use std::io::{Cursor, Read};
+use thiserror::Error;
+use zip::ZipArchive;
+use zip::result::ZipError;
+
+use crate::types::{Package, Platform};
+
+/// Bytes read from the start of each file to recognise executables. Go places the PE header well
+/// within this.
+const HEADER_BYTES: u64 = 4096;
+
+/// Checks applied to the contents of provider packages.
+#[derive(Deserialize, Serialize, PartialEq, Clone, Debug)]
+pub struct PackageCheckConfig {
+    /// Largest size accepted for a package, and for any file in it once uncompressed.
+    #[serde(default = "default_max_file_bytes")]
+    pub max_file_bytes: u64,
+}
+
+fn default_max_file_bytes() -> u64 {
+    2 * 1024 * 1024 * 1024
+}
+
+impl Default for PackageCheckConfig {
+    fn default() -> Self {
+        Self {
+            max_file_bytes: default_max_file_bytes(),
+        }
+    }
+}
+
+/// Describes what is wrong with a package, phrased to follow its filename.
+#[derive(Error, Debug)]
+pub enum PackageError {
+    #[error("is not a valid zip archive: {0}")]
+    InvalidArchive(#[from] ZipError),
+    #[error("does not contain `{0}`")]
+    MissingExecutable(String),
+    #[error("contains `{0}`, which is not an executable")]
+    NotExecutable(String),
+    #[error("contains another executable, `{0}`")]
+    ExtraExecutable(String),
+    #[error("contains `{name}` of {size} bytes, more than the limit of {limit}")]
+    TooLarge { name: String, size: u64, limit: u64 },
+    #[error("contains `{name}` built for {found}, not {os}_{arch}")]
+    PlatformMismatch {
+        name: String,
+        found: Executable,
+        os: String,
+        arch: String,
+    },
+    #[error("could not be downloaded: {0}")]
+    Download(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Elf,
+    MachO,
+    Pe,
+}
+
+impl Format {
+    /// The executable format Go produces for `os`, if it is one the registry knows.
+    fn for_os(os: &str) -> Option<Self> {
+        match os {
+            "darwin" => Some(Self::MachO),
+            "windows" => Some(Self::Pe),
+            "linux" | "freebsd" | "openbsd" | "netbsd" | "solaris" => Some(Self::Elf),
+            _ => None,
+        }
+    }
+}
+
+/// Format and architectures read from an executable's header. Universal Mach-O binaries have
+/// several architectures; unrecognised machine types have none.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Executable {
+    format: Format,
+    arches: Vec<&'static str>,
+}
+
+impl fmt::Display for Executable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let format = match self.format {
+            Format::Elf => "ELF",
+            Format::MachO => "Mach-O",
+            Format::Pe => "PE",
+        };
+        if self.arches.is_empty() {
+            write!(f, "{format} (unknown architecture)")
+        } else {
+            write!(f, "{format} {}", self.arches.join("/"))
+        }
+    }
+}
+
+/// Architectures the header checks can identify. Packages declared for any other architecture
+/// only need to contain an executable of the right format.
+const KNOWN_ARCHES: &[&str] = &[
+    "386", "amd64", "arm", "arm64", "loong64", "ppc64", "ppc64le", "riscv64", "s390x",
+];
+
+impl Executable {
+    fn identify(header: &[u8]) -> Option<Self> {
+        match header.get(..4)? {
+            [0x7f, b'E', b'L', b'F'] => Some(Self::elf(header)),
+            [0xcf | 0xce, 0xfa, 0xed, 0xfe] => Some(Self::mach_o(read_u32(header, 4, false)?)),
+            [0xfe, 0xed, 0xfa, 0xcf | 0xce] => Some(Self::mach_o(read_u32(header, 4, true)?)),
+            [0xca, 0xfe, 0xba, 0xbe] => Self::universal(header),
+            [b'M', b'Z', ..] => Self::pe(header),
+            _ => None,
+        }
+    }
+
+    fn elf(header: &[u8]) -> Self {
+        let is_64 = header.get(4) == Some(&2);
+        let big_endian = header.get(5) == Some(&2);
+        let arch = match read_u16(header, 18, big_endian) {
+            Some(0x03) => Some("386"),
+            Some(0x3e) => Some("amd64"),
+            Some(0x28) => Some("arm"),
+            Some(0xb7) => Some("arm64"),
+            Some(0x15) if is_64 && big_endian => Some("ppc64"),
+            Some(0x15) if is_64 => Some("ppc64le"),
+            Some(0x16) => Some("s390x"),
+            Some(0xf3) if is_64 => Some("riscv64"),
+            Some(0x102) => Some("loong64"),
+            _ => None,
+        };
+        Self {
+            format: Format::Elf,
+            arches: arch.into_iter().collect(),
+        }
+    }
+
+    fn mach_o_arch(cpu_type: u32) -> Option<&'static str> {
+        match cpu_type {
+            0x0000_0007 => Some("386"),
+            0x0100_0007 => Some("amd64"),
+            0x0000_000c => Some("arm"),
+            0x0100_000c => Some("arm64"),
+            _ => None,
+        }
+    }
+
+    fn mach_o(cpu_type: u32) -> Self {
+        Self {
+            format: Format::MachO,
+            arches: Self::mach_o_arch(cpu_type).into_iter().collect(),
+        }
+    }
+
+    /// A universal binary: a big-endian count of architectures followed by 20-byte entries that
+    /// start with their CPU type.
+    fn universal(header: &[u8]) -> Option<Self> {
+        let count = usize::try_from(read_u32(header, 4, true)?).ok()?;
+        let arches = (0..count)
+            .map_while(|i| read_u32(header, 8 + i * 20, true))
+            .filter_map(Self::mach_o_arch)
+            .collect();
+        Some(Self {
+            format: Format::MachO,
+            arches,
+        })
+    }
+
+    fn pe(header: &[u8]) -> Option<Self> {
+        let offset = usize::try_from(read_u32(header, 0x3c, false)?).ok()?;
+        if header.get(offset..offset + 4)? != b"PE\0\0" {
+            return None;
+        }
+        let arch = match read_u16(header, offset + 4, false)? {
+            0x014c => Some("386"),
+            0x8664 => Some("amd64"),
+            0x01c0 | 0x01c4 => Some("arm"),
+            0xaa64 => Some("arm64"),
+            _ => None,
+        };
+        Some(Self {
+            format: Format::Pe,
+            arches: arch.into_iter().collect(),
+        })
+    }
+
+    /// Whether this executable can run on `platform`, as far as the header tells.
+    fn runs_on(&self, platform: &Platform) -> bool {
+        if Format::for_os(&platform.os).is_some_and(|format| format != self.format) {
+            return false;
+        }
+        self.arches.contains(&platform.arch.as_str())
+            || !KNOWN_ARCHES.contains(&platform.arch.as_str())
+    }
+}
+
+fn read_u16(bytes: &[u8], offset: usize, big_endian: bool) -> Option<u16> {
+    let bytes = bytes.get(offset..offset + 2)?.try_into().ok()?;
+    Some(if big_endian {
+        u16::from_be_bytes(bytes)
+    } else {
+        u16::from_le_bytes(bytes)
+    })
+}
+
+fn read_u32(bytes: &[u8], offset: usize, big_endian: bool) -> Option<u32> {
+    let bytes = bytes.get(offset..offset + 4)?.try_into().ok()?;
+    Some(if big_endian {
+        u32::from_be_bytes(bytes)
+    } else {
+        u32::from_le_bytes(bytes)
+    })
+}
+
+/// Checks that a package contains exactly one executable, named the way Terraform expects for
+/// `provider_type` at `version` and built for `platform`. Other files, such as licenses, are
+/// allowed.
+pub fn check_package(
+    archive: &[u8],
+    provider_type: &str,
+    version: &str,
+    platform: &Platform,
+    config: &PackageCheckConfig,
+) -> Result<(), PackageError> {
+    let mut expected = format!("terraform-provider-{provider_type}_v{version}");
+    if platform.os == "windows" {
+        expected.push_str(".exe");
+    }
+
+    let mut archive = ZipArchive::new(Cursor::new(archive))?;
+    let mut found = false;
+    let mut extra = None;
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index)?;
+        if file.is_dir() {
+            continue;
+        }
+        let name = file.name().to_string();
+        if file.size() > config.max_file_bytes {
+            return Err(PackageError::TooLarge {
+                name,
+                size: file.size(),
+                limit: config.max_file_bytes,
+            });
+        }
+
+        let mut header = Vec::new();
+        file.by_ref()
+            .take(HEADER_BYTES)
+            .read_to_end(&mut header)
+            .map_err(ZipError::from)?;
+        let executable = Executable::identify(&header);
+
+        if name == expected && !found {
+            let executable = executable.ok_or_else(|| PackageError::NotExecutable(name.clone()))?;
+            if !executable.runs_on(platform) {
+                return Err(PackageError::PlatformMismatch {
+                    name,
+                    found: executable,
+                    os: platform.os.clone(),
+                    arch: platform.arch.clone(),
+                });
+            }
+            found = true;
+        } else if extra.is_none() && (name == expected || executable.is_some()) {
+            extra = Some(name);
+        }
+    }
+
+    if !found {
+        return Err(PackageError::MissingExecutable(expected));
+    }
+    match extra {
+        Some(name) => Err(PackageError::ExtraExecutable(name)),
+        None => Ok(()),
+    }
+}
+
+/// Downloads packages so the index sync can check them before advertising them.
+pub struct PackageVerifier {
+    client: Client,
+    config: PackageCheckConfig,
+}
+
+impl PackageVerifier {
+    pub fn new(config: PackageCheckConfig) -> Self {
+        Self {
+            client: Client::new(),
+            config,
+        }
+    }
+
+    pub fn verify(
+        &self,
+        provider_type: &str,
+        version: &str,
+        package: &Package,
+    ) -> Result<(), PackageError> {
+        let download = |error: reqwest::Error| PackageError::Download(error.to_string());
+        let response = self
+            .client
+            .get(&package.download_url)
+            .send()
+            .and_then(reqwest::blocking::Response::error_for_status)
+            .map_err(download)?;
+
+        let mut archive = Vec::new();
+        response
+            .take(self.config.max_file_bytes + 1)
+            .read_to_end(&mut archive)
+            .map_err(|error| PackageError::Download(error.to_string()))?;
+        let size = archive.len() as u64;
+        if size > self.config.max_file_bytes {
+            return Err(PackageError::TooLarge {
+                name: package.filename.clone(),
+                size,
+                limit: self.config.max_file_bytes,
+            });
+        }
+
+        check_package(
+            &archive,
+            provider_type,
+            version,
+            &Platform {
+                os: package.os.clone(),
+                arch: package.arch.clone(),
+            },
+            &self.config,
+        )
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::ZipWriter;
+    use zip::write::SimpleFileOptions;
+
+    /// The start of an executable for `os`/`arch`, as far as `Executable::identify` reads it.
+    pub fn executable(os: &str, arch: &str) -> Vec<u8> {
+        match os {
+            "darwin" => {
+                let cpu_type: u32 = match arch {
+                    "amd64" => 0x0100_0007,
+                    "arm64" => 0x0100_000c,
+                    _ => 0,
+                };
+                let mut header = vec![0xcf, 0xfa, 0xed, 0xfe];
+                header.extend(cpu_type.to_le_bytes());
+                header
+            }
+            "windows" => {
+                let machine: u16 = match arch {
+                    "386" => 0x014c,
+                    "amd64" => 0x8664,
+                    _ => 0,
+                };
+                let mut header = vec![0; 0x40];
+                header[..2].copy_from_slice(b"MZ");
+                header[0x3c] = 0x40;
+                header.extend(b"PE\0\0");
+                header.extend(machine.to_le_bytes());
+                header
+            }
+            _ => {
+                let machine: u16 = match arch {
+                    "386" => 0x03,
+                    "amd64" => 0x3e,
+                    "arm" => 0x28,
+                    "arm64" => 0xb7,
+                    _ => 0,
+                };
+                let mut header = vec![0; 20];
+                header[..6].copy_from_slice(&[0x7f, b'E', b'L', b'F', 2, 1]);
+                header[18..].copy_from_slice(&machine.to_le_bytes());
+                header
+            }
+        }
+    }
+
+    pub fn zip(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, contents) in files {
+            writer
+                .start_file(*name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(contents).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    /// A well-formed package of `terraform-provider-{provider_type}` at `version`.
+    pub fn provider_zip(provider_type: &str, version: &str, os: &str, arch: &str) -> Vec<u8> {
+        let mut name = format!("terraform-provider-{provider_type}_v{version}");
+        if os == "windows" {
+            name.push_str(".exe");
+        }
+        zip(&[(&name, &executable(os, arch)), ("LICENSE", b"MIT")])
+    }
+
+    fn check(archive: &[u8], os: &str, arch: &str) -> Result<(), PackageError> {
+        check_package(
+            archive,
+            "foo",
+            "1.0.0",
+            &Platform {
+                os: os.to_string(),
+                arch: arch.to_string(),
+            },
+            &PackageCheckConfig::default(),
+        )
+    }
+
+    #[test]
+    fn accepts_executables_for_the_declared_platform() {
+        for (os, arch) in [
+            ("linux", "amd64"),
+            ("linux", "arm"),
+            ("darwin", "arm64"),
+            ("windows", "386"),
+        ] {
+            check(&provider_zip("foo", "1.0.0", os, arch), os, arch).unwrap();
+        }
+    }
+
+    #[test]
+    fn rejects_executables_for_another_platform() {
+        let result = check(
+            &provider_zip("foo", "1.0.0", "linux", "amd64"),
+            "linux",
+            "arm64",
+        );
+        let wrong_os = check(
+            &provider_zip("foo", "1.0.0", "linux", "arm64"),
+            "darwin",
+            "arm64",
+        );
+
+        assert!(matches!(
+            &result,
+            Err(PackageError::PlatformMismatch { found, .. }) if found.to_string() == "ELF amd64"
+        ));
+        assert!(matches!(
+            wrong_os,
+            Err(PackageError::PlatformMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_misnamed_and_extra_executables() {
+        let misnamed = zip(&[(
+            "terraform-provider-foo_1.0.0",
+            &executable("linux", "amd64"),
+        )]);
+        let extra = zip(&[
+            (
+                "terraform-provider-foo_v1.0.0",
+                &executable("linux", "amd64"),
+            ),
+            ("helper", &executable("linux", "amd64")),
+        ]);
+        let not_executable = zip(&[("terraform-provider-foo_v1.0.0", b"#!/bin/sh")]);
+
+        assert!(matches!(
+            check(&misnamed, "linux", "amd64"),
+            Err(PackageError::MissingExecutable(name)) if name == "terraform-provider-foo_v1.0.0"
+        ));
+        assert!(matches!(
+            check(&extra, "linux", "amd64"),
+            Err(PackageError::ExtraExecutable(name)) if name == "helper"
+        ));
+        assert!(matches!(
+            check(&not_executable, "linux", "amd64"),
+            Err(PackageError::NotExecutable(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_oversized_files_and_non_zips() {
+        let result = check_package(
+            &provider_zip("foo", "1.0.0", "linux", "amd64"),
+            "foo",
+            "1.0.0",
+            &Platform {
+                os: "linux".to_string(),
+                arch: "amd64".to_string(),
+            },
+            &PackageCheckConfig { max_file_bytes: 8 },
+        );
+
+        assert!(matches!(
+            result,
+            Err(PackageError::TooLarge { size: 20, .. })
+        ));
+        assert!(matches!(
+            check(b"linux", "linux", "amd64"),
+            Err(PackageError::InvalidArchive(_))
+        ));
+    }
+}
@@ -0,0 +1,222 @@
+use axum::{
+    Json,
+    extract::{FromRequestParts, Path},
+    http::request::Parts,
+    response::{IntoResponse, Response},
+};
+use serde::de::DeserializeOwned;
+use serde_derive::{Deserialize, Serialize};
+use std::fmt;
+use std::ops::Deref;
+use std::str::FromStr;
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq)]
+#[error("`{value}` is not a valid {what}: {rule}")]
+pub struct InvalidParam {
+    what: &'static str,
+    value: String,
+    rule: &'static str,
+}
+
+/// Terraform's rule for namespaces and provider types: letters, digits and dashes, without
+/// leading, trailing or doubled dashes.
+fn is_provider_part(value: &str) -> bool {
+    !value.is_empty()
+        && value.len() <= 64
+        && value
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-')
+        && !value.starts_with('-')
+        && !value.ends_with('-')
+        && !value.contains("--")
+}
+
+fn is_platform_part(value: &str) -> bool {
+    !value.is_empty()
+        && value
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit())
+}
+
+fn is_version(value: &str) -> bool {
+    semver::Version::parse(value).is_ok()
+}
+
+/// Declares a string newtype that can only hold values passing `$valid`, and that deserializes
+/// (e.g. from a path segment or the config file) only when they do.
+macro_rules! validated_string {
+    ($(#[$meta:meta])* $name:ident, $what:literal, $valid:path, $rule:literal) => {
+        $(#[$meta])*
+        #[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+        #[serde(try_from = "String", into = "String")]
+        pub struct $name(String);
+
+        impl $name {
+            pub fn new(value: impl Into<String>) -> Result<Self, InvalidParam> {
+                let value = value.into();
+                if $valid(&value) {
+                    Ok(Self(value))
+                } else {
+                    Err(InvalidParam {
+                        what: $what,
+                        value,
+                        rule: $rule,
+                    })
+                }
+            }
+
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl Deref for $name {
+            type Target = str;
+
+            fn deref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = InvalidParam;
+
+            fn from_str(value: &str) -> Result<Self, Self::Err> {
+                Self::new(value)
+            }
+        }
+
+        impl TryFrom<String> for $name {
+            type Error = InvalidParam;
+
+            fn try_from(value: String) -> Result<Self, Self::Error> {
+                Self::new(value)
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(value: $name) -> Self {
+                value.0
+            }
+        }
+    };
+}
+
+validated_string!(
+    /// The organisation a provider is published under, e.g. `hashicorp`.
+    Namespace,
+    "namespace",
+    is_provider_part,
+    "use letters, digits and single dashes, not at the start or end"
+);
+
+validated_string!(
+    /// The name of a provider within its namespace, e.g. `aws`.
+    ProviderType,
+    "provider type",
+    is_provider_part,
+    "use letters, digits and single dashes, not at the start or end"
+);
+
+validated_string!(
+    /// A provider version, kept as written.
+    Version,
+    "version",
+    is_version,
+    "it must be a semantic version such as 1.2.3"
+);
+
+validated_string!(
+    /// A target operating system, as Go names them, e.g. `linux`.
+    Os,
+    "operating system",
+    is_platform_part,
+    "use lowercase letters and digits"
+);
+
+validated_string!(
+    /// A target architecture, as Go names them, e.g. `amd64`.
+    Arch,
+    "architecture",
+    is_platform_part,
+    "use lowercase letters and digits"
+);
+
+/// Like `Path`, but answers parameters that fail to parse with a 400 and the `{"errors": [...]}`
+/// body the Terraform CLI shows to users, before any handler runs.
+pub struct ValidPath<T>(pub T);
+
+impl<T, S> FromRequestParts<S> for ValidPath<T>
+where
+    T: DeserializeOwned + Send,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        match Path::<T>::from_request_parts(parts, state).await {
+            Ok(Path(value)) => Ok(Self(value)),
+            Err(rejection) => Err((
+                rejection.status(),
+                Json(serde_json::json!({ "errors": [rejection.body_text()] })),
+            )
+                .into_response()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn provider_parts_follow_terraform_rules() {
+        assert!(Namespace::new("hashicorp").is_ok());
+        assert!(Namespace::new("my-org2").is_ok());
+        assert!(ProviderType::new("google-beta").is_ok());
+
+        for invalid in [
+            "",
+            "-acme",
+            "acme-",
+            "ac--me",
+            "acme_corp",
+            "acme.io",
+            "a/b",
+        ] {
+            assert!(Namespace::new(invalid).is_err(), "{invalid}");
+        }
+        assert!(ProviderType::new("a".repeat(65)).is_err());
+    }
+
+    #[test]
+    fn versions_and_platforms_are_checked() {
+        assert!(Version::new("1.2.3-beta.1+build.5").is_ok());
+        assert!(Version::new("1.2").is_err());
+        assert!(Version::new("v1.2.3").is_err());
+        assert!(Os::new("linux").is_ok());
+        assert!(Arch::new("arm64").is_ok());
+        assert!(Os::new("Linux").is_err());
+        assert!(Arch::new("amd64;").is_err());
+
+        assert_eq!(
+            Version::new("latest").unwrap_err().to_string(),
+            "`latest` is not a valid version: it must be a semantic version such as 1.2.3"
+        );
+    }
+
+    #[test]
+    fn deserializes_only_valid_values() {
+        let namespace: Namespace = serde_json::from_str(r#""acme""#).unwrap();
+
+        assert_eq!(namespace.as_str(), "acme");
+        assert!(serde_json::from_str::<Namespace>(r#""ac me""#).is_err());
+    }
+}
@@ -0,0 +1,669 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use crate::config::CacheConfig;
+use crate::index::ProviderRef;
+use crate::metrics::Metrics;
+use crate::params::{Arch, Namespace, Os, ProviderType, Version};
+use crate::types::{Package, ReleaseDiagnostic, VersionInfo};
+use tracing::warn;
+
+use super::{Backend, Freshness, ProviderBackendError, ProviderDetails, Result, Staleness};
+
+type VersionsKey = (Namespace, ProviderType);
+type PackageKey = (Namespace, ProviderType, Version, Os, Arch);
+
+/// Wraps another backend and remembers its answers for a while, so that repeated lookups do not
+/// reach the upstream. `NotFound` answers are remembered separately, with their own TTL; any other
+/// error is never cached.
+///
+/// The provider catalogue and provider details are cached the same way, so that searching does not
+/// describe every provider upstream on each request.
+///
+/// Provider listings may additionally be served past their TTL: within the stale-while-revalidate
+/// window the old listing is returned at once while a background refresh runs, and within the
+/// stale-if-error window it stands in for a failed upstream call.
+pub struct CachingBackend {
+    inner: Arc<dyn Backend>,
+    ttl: Duration,
+    negative_ttl: Duration,
+    metrics: Metrics,
+    versions: Arc<TtlCache<VersionsKey, Vec<VersionInfo>>>,
+    packages: Arc<TtlCache<PackageKey, Package>>,
+    providers: Arc<TtlCache<(), Vec<ProviderRef>>>,
+    details: Arc<TtlCache<VersionsKey, ProviderDetails>>,
+}
+
+impl CachingBackend {
+    pub fn new(inner: Arc<dyn Backend>, config: &CacheConfig, metrics: Metrics) -> Self {
+        Self {
+            inner,
+            ttl: Duration::from_secs(config.ttl_secs),
+            negative_ttl: Duration::from_secs(config.negative_ttl_secs),
+            metrics,
+            versions: Arc::new(TtlCache::new(
+                "versions",
+                config.max_entries,
+                Duration::from_secs(config.stale_while_revalidate_secs),
+                Duration::from_secs(config.stale_if_error_secs),
+            )),
+            packages: Arc::new(TtlCache::new(
+                "packages",
+                config.max_entries,
+                Duration::ZERO,
+                Duration::ZERO,
+            )),
+            providers: Arc::new(TtlCache::new(
+                "providers",
+                config.max_entries,
+                Duration::ZERO,
+                Duration::ZERO,
+            )),
+            details: Arc::new(TtlCache::new(
+                "details",
+                config.max_entries,
+                Duration::ZERO,
+                Duration::ZERO,
+            )),
+        }
+    }
+
+    fn get_or_load<K, V>(
+        &self,
+        cache: &Arc<TtlCache<K, V>>,
+        key: K,
+        load: impl Fn() -> Result<V> + Send + 'static,
+    ) -> Result<(V, Option<Freshness>)>
+    where
+        K: Eq + Hash + Clone + Send + Sync + 'static,
+        V: Clone + Send + Sync + 'static,
+    {
+        let fallback = match cache.lookup(&key) {
+            Lookup::Fresh(Cached::Found(value), age) => {
+                self.metrics.record_cache_lookup(cache.name, "hit");
+                return Ok((value, Some(freshness(age, Staleness::Fresh))));
+            }
+            Lookup::Fresh(Cached::NotFound, _) => {
+                self.metrics.record_cache_lookup(cache.name, "negative_hit");
+                return Err(ProviderBackendError::NotFound);
+            }
+            Lookup::Stale(value, age, refresh) => {
+                self.metrics.record_cache_lookup(cache.name, "stale_hit");
+                if refresh {
+                    self.refresh_in_background(cache, key, load);
+                }
+                return Ok((value, Some(freshness(age, Staleness::Revalidating))));
+            }
+            Lookup::Miss(fallback) => fallback,
+        };
+        self.metrics.record_cache_lookup(cache.name, "miss");
+
+        let result = load();
+        cache.store(&key, &result, self.ttl, self.negative_ttl);
+        match (result, fallback) {
+            (Ok(value), _) => Ok((value, None)),
+            (Err(error), Some((value, age))) if error.is_unavailable() => {
+                self.metrics
+                    .record_cache_lookup(cache.name, "stale_if_error");
+                Ok((value, Some(freshness(age, Staleness::RevalidationFailed))))
+            }
+            (Err(error), _) => Err(error),
+        }
+    }
+
+    fn refresh_in_background<K, V>(
+        &self,
+        cache: &Arc<TtlCache<K, V>>,
+        key: K,
+        load: impl Fn() -> Result<V> + Send + 'static,
+    ) where
+        K: Eq + Hash + Clone + Send + Sync + 'static,
+        V: Clone + Send + Sync + 'static,
+    {
+        let cache = cache.clone();
+        let (ttl, negative_ttl) = (self.ttl, self.negative_ttl);
+        let span = tracing::info_span!("cache_refresh", cache = cache.name);
+
+        std::thread::spawn(move || {
+            span.in_scope(|| {
+                let result = load();
+                if let Err(error) = &result {
+                    warn!("Background refresh failed, keeping stale entry: {error}");
+                }
+                cache.store(&key, &result, ttl, negative_ttl);
+                cache.finish_refresh(&key);
+            });
+        });
+    }
+}
+
+fn freshness(age: Duration, staleness: Staleness) -> Freshness {
+    Freshness { age, staleness }
+}
+
+impl Backend for CachingBackend {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn health(&self) -> Result<()> {
+        self.inner.health()
+    }
+
+    fn list_provider_versions(
+        &self,
+        namespace: Namespace,
+        provider_type: ProviderType,
+    ) -> Result<Vec<VersionInfo>> {
+        self.list_provider_versions_with_freshness(namespace, provider_type)
+            .map(|(versions, _)| versions)
+    }
+
+    fn list_provider_versions_with_freshness(
+        &self,
+        namespace: Namespace,
+        provider_type: ProviderType,
+    ) -> Result<(Vec<VersionInfo>, Option<Freshness>)> {
+        let inner = self.inner.clone();
+        let key = (namespace.clone(), provider_type.clone());
+        self.get_or_load(&self.versions, key, move || {
+            inner.list_provider_versions(namespace.clone(), provider_type.clone())
+        })
+    }
+
+    fn find_provider_package(
+        &self,
+        namespace: Namespace,
+        provider_type: ProviderType,
+        version: Version,
+        os: Os,
+        arch: Arch,
+    ) -> Result<Package> {
+        let inner = self.inner.clone();
+        let key = (
+            namespace.clone(),
+            provider_type.clone(),
+            version.clone(),
+            os.clone(),
+            arch.clone(),
+        );
+        self.get_or_load(&self.packages, key, move || {
+            inner.find_provider_package(
+                namespace.clone(),
+                provider_type.clone(),
+                version.clone(),
+                os.clone(),
+                arch.clone(),
+            )
+        })
+        .map(|(package, _)| package)
+    }
+
+    fn diagnose_provider(
+        &self,
+        namespace: Namespace,
+        provider_type: ProviderType,
+    ) -> Result<Vec<ReleaseDiagnostic>> {
+        self.inner.diagnose_provider(namespace, provider_type)
+    }
+
+    fn list_providers(&self) -> Result<Vec<ProviderRef>> {
+        let inner = self.inner.clone();
+        self.get_or_load(&self.providers, (), move || inner.list_providers())
+            .map(|(providers, _)| providers)
+    }
+
+    fn describe_provider(
+        &self,
+        namespace: Namespace,
+        provider_type: ProviderType,
+    ) -> Result<ProviderDetails> {
+        let inner = self.inner.clone();
+        let key = (namespace.clone(), provider_type.clone());
+        self.get_or_load(&self.details, key, move || {
+            inner.describe_provider(namespace.clone(), provider_type.clone())
+        })
+        .map(|(details, _)| details)
+    }
+
+    /// Refreshes the wrapped backend first, so a read racing the eviction cannot cache what the
+    /// index held before.
+    fn refresh_provider(&self, namespace: Namespace, provider_type: ProviderType) -> Result<()> {
+        self.inner
+            .refresh_provider(namespace.clone(), provider_type.clone())?;
+        self.versions
+            .remove_where(|(ns, pt)| *ns == namespace && *pt == provider_type);
+        self.packages
+            .remove_where(|(ns, pt, ..)| *ns == namespace && *pt == provider_type);
+        self.details
+            .remove_where(|(ns, pt)| *ns == namespace && *pt == provider_type);
+        // A first publish adds the provider to the catalogue.
+        self.providers.remove_where(|()| true);
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+enum Cached<V> {
+    Found(V),
+    NotFound,
+}
+
+enum Lookup<V> {
+    /// Within its TTL.
+    Fresh(Cached<V>, Duration),
+    /// Within the stale-while-revalidate window; `true` when the caller should start the refresh.
+    Stale(V, Duration, bool),
+    /// Must be loaded again, with the stale value to fall back on if the upstream fails.
+    Miss(Option<(V, Duration)>),
+}
+
+struct Entry<V> {
+    value: Cached<V>,
+    stored_at: Instant,
+    expires_at: Instant,
+    refreshing: bool,
+}
+
+struct TtlCache<K, V> {
+    name: &'static str,
+    max_entries: usize,
+    stale_while_revalidate: Duration,
+    stale_if_error: Duration,
+    entries: Mutex<HashMap<K, Entry<V>>>,
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> TtlCache<K, V> {
+    fn new(
+        name: &'static str,
+        max_entries: usize,
+        stale_while_revalidate: Duration,
+        stale_if_error: Duration,
+    ) -> Self {
+        Self {
+            name,
+            max_entries,
+            stale_while_revalidate,
+            stale_if_error,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn lookup(&self, key: &K) -> Lookup<V> {
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        let now = Instant::now();
+
+        let Some(entry) = entries.get_mut(key) else {
+            return Lookup::Miss(None);
+        };
+        let age = now.saturating_duration_since(entry.stored_at);
+
+        if now < entry.expires_at {
+            return Lookup::Fresh(entry.value.clone(), age);
+        }
+        let Cached::Found(value) = &entry.value else {
+            return Lookup::Miss(None);
+        };
+
+        if now < entry.expires_at + self.stale_while_revalidate {
+            let refresh = !entry.refreshing;
+            entry.refreshing = true;
+            Lookup::Stale(value.clone(), age, refresh)
+        } else if now < entry.expires_at + self.stale_if_error {
+            Lookup::Miss(Some((value.clone(), age)))
+        } else {
+            Lookup::Miss(None)
+        }
+    }
+
+    /// Caches a fresh answer or a `NotFound`; other errors leave any existing entry in place.
+    fn store(&self, key: &K, result: &Result<V>, ttl: Duration, negative_ttl: Duration) {
+        match result {
+            Ok(value) => self.insert(key.clone(), Cached::Found(value.clone()), ttl),
+            Err(ProviderBackendError::NotFound) => {
+                self.insert(key.clone(), Cached::NotFound, negative_ttl);
+            }
+            Err(_) => {}
+        }
+    }
+
+    fn remove_where(&self, matches: impl Fn(&K) -> bool) {
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        entries.retain(|key, _| !matches(key));
+    }
+
+    fn finish_refresh(&self, key: &K) {
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(entry) = entries.get_mut(key) {
+            entry.refreshing = false;
+        }
+    }
+
+    /// Time after which an entry is of no further use, not even as a stale fallback.
+    fn retain_until(&self, entry: &Entry<V>) -> Instant {
+        match entry.value {
+            Cached::Found(_) => {
+                entry.expires_at + self.stale_while_revalidate.max(self.stale_if_error)
+            }
+            Cached::NotFound => entry.expires_at,
+        }
+    }
+
+    fn insert(&self, key: K, value: Cached<V>, ttl: Duration) {
+        let now = Instant::now();
+        let entry = Entry {
+            value,
+            stored_at: now,
+            expires_at: now + ttl,
+            refreshing: false,
+        };
+        if self.max_entries == 0 || self.retain_until(&entry) <= now {
+            return;
+        }
+
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if entries.len() >= self.max_entries && !entries.contains_key(&key) {
+            entries.retain(|_, entry| self.retain_until(entry) > now);
+        }
+        if entries.len() >= self.max_entries && !entries.contains_key(&key) {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.expires_at)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+
+        entries.insert(key, entry);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::FakeBackend;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    /// Counts calls reaching it. The `missing` and `broken` namespaces answer `NotFound` and
+    /// `StorageError` respectively, as does every call while `failing` is set.
+    #[derive(Default)]
+    struct CountingBackend {
+        calls: AtomicUsize,
+        failing: AtomicBool,
+    }
+
+    impl CountingBackend {
+        fn calls(&self) -> usize {
+            self.calls.load(Ordering::SeqCst)
+        }
+    }
+
+    impl Backend for CountingBackend {
+        fn name(&self) -> &'static str {
+            "counting"
+        }
+
+        fn health(&self) -> Result<()> {
+            Ok(())
+        }
+
+        fn list_provider_versions(
+            &self,
+            namespace: Namespace,
+            provider_type: ProviderType,
+        ) -> Result<Vec<VersionInfo>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if self.failing.load(Ordering::SeqCst) {
+                return Err(ProviderBackendError::storage("unavailable"));
+            }
+            match namespace.as_str() {
+                "missing" => Err(ProviderBackendError::NotFound),
+                "broken" => Err(ProviderBackendError::storage("unavailable")),
+                _ => FakeBackend.list_provider_versions(namespace, provider_type),
+            }
+        }
+
+        fn find_provider_package(
+            &self,
+            namespace: Namespace,
+            provider_type: ProviderType,
+            version: Version,
+            os: Os,
+            arch: Arch,
+        ) -> Result<Package> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            FakeBackend.find_provider_package(namespace, provider_type, version, os, arch)
+        }
+
+        fn list_providers(&self) -> Result<Vec<ProviderRef>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(vec![ProviderRef {
+                namespace: "hashicorp".parse().unwrap(),
+                provider_type: "aws".parse().unwrap(),
+            }])
+        }
+
+        fn describe_provider(
+            &self,
+            _namespace: Namespace,
+            _provider_type: ProviderType,
+        ) -> Result<ProviderDetails> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(ProviderDetails {
+                description: Some("Amazon Web Services".to_string()),
+                ..ProviderDetails::default()
+            })
+        }
+    }
+
+    fn caching(inner: &Arc<CountingBackend>, config: &CacheConfig) -> CachingBackend {
+        CachingBackend::new(inner.clone(), config, Metrics::new().unwrap())
+    }
+
+    fn config(ttl_secs: u64, max_entries: usize) -> CacheConfig {
+        CacheConfig {
+            ttl_secs,
+            negative_ttl_secs: ttl_secs,
+            max_entries,
+            stale_while_revalidate_secs: 0,
+            stale_if_error_secs: 0,
+        }
+    }
+
+    fn list_with_freshness(
+        backend: &CachingBackend,
+        namespace: &str,
+    ) -> Result<(Vec<VersionInfo>, Option<Freshness>)> {
+        backend.list_provider_versions_with_freshness(
+            namespace.parse().unwrap(),
+            "aws".parse().unwrap(),
+        )
+    }
+
+    fn list(backend: &CachingBackend, namespace: &str) -> Result<Vec<VersionInfo>> {
+        backend.list_provider_versions(namespace.parse().unwrap(), "aws".parse().unwrap())
+    }
+
+    #[test]
+    fn repeated_version_lookups_within_ttl_hit_inner_once() {
+        let inner = Arc::new(CountingBackend::default());
+        let backend = caching(&inner, &config(60, 10));
+
+        for _ in 0..5 {
+            assert_eq!(list(&backend, "hashicorp").unwrap().len(), 2);
+        }
+
+        assert_eq!(inner.calls(), 1);
+    }
+
+    #[test]
+    fn repeated_package_lookups_within_ttl_hit_inner_once() {
+        let inner = Arc::new(CountingBackend::default());
+        let backend = caching(&inner, &config(60, 10));
+
+        for _ in 0..5 {
+            backend
+                .find_provider_package(
+                    "hashicorp".parse().unwrap(),
+                    "aws".parse().unwrap(),
+                    "1.0.0".parse().unwrap(),
+                    "linux".parse().unwrap(),
+                    "amd64".parse().unwrap(),
+                )
+                .unwrap();
+        }
+
+        assert_eq!(inner.calls(), 1);
+    }
+
+    #[test]
+    fn catalogue_and_details_are_cached_until_refreshed() {
+        let inner = Arc::new(CountingBackend::default());
+        let backend = caching(&inner, &config(60, 10));
+        let describe = || {
+            backend
+                .describe_provider("hashicorp".parse().unwrap(), "aws".parse().unwrap())
+                .unwrap()
+        };
+
+        for _ in 0..3 {
+            assert_eq!(backend.list_providers().unwrap().len(), 1);
+            assert!(describe().description.is_some());
+        }
+        assert_eq!(inner.calls(), 2);
+
+        backend
+            .refresh_provider("hashicorp".parse().unwrap(), "aws".parse().unwrap())
+            .unwrap();
+        backend.list_providers().unwrap();
+        describe();
+        assert_eq!(inner.calls(), 4);
+    }
+
+    #[test]
+    fn not_found_is_negatively_cached() {
+        let inner = Arc::new(CountingBackend::default());
+        let backend = caching(&inner, &config(60, 10));
+
+        for _ in 0..3 {
+            assert!(matches!(
+                list(&backend, "missing"),
+                Err(ProviderBackendError::NotFound)
+            ));
+        }
+
+        assert_eq!(inner.calls(), 1);
+    }
+
+    #[test]
+    fn storage_errors_are_not_cached() {
+        let inner = Arc::new(CountingBackend::default());
+        let backend = caching(&inner, &config(60, 10));
+
+        for _ in 0..3 {
+            assert!(list(&backend, "broken").is_err());
+        }
+
+        assert_eq!(inner.calls(), 3);
+    }
+
+    #[test]
+    fn expired_entries_reach_inner() {
+        let inner = Arc::new(CountingBackend::default());
+        let backend = caching(&inner, &config(0, 10));
+
+        list(&backend, "hashicorp").unwrap();
+        list(&backend, "hashicorp").unwrap();
+
+        assert_eq!(inner.calls(), 2);
+    }
+
+    #[test]
+    fn max_entries_evicts_oldest() {
+        let inner = Arc::new(CountingBackend::default());
+        let backend = caching(&inner, &config(60, 2));
+
+        list(&backend, "first").unwrap();
+        list(&backend, "second").unwrap();
+        list(&backend, "third").unwrap();
+        assert_eq!(inner.calls(), 3);
+
+        list(&backend, "third").unwrap();
+        list(&backend, "second").unwrap();
+        assert_eq!(inner.calls(), 3);
+
+        list(&backend, "first").unwrap();
+        assert_eq!(inner.calls(), 4);
+    }
+
+    #[test]
+    fn fresh_hits_report_their_age() {
+        let inner = Arc::new(CountingBackend::default());
+        let backend = caching(&inner, &config(60, 10));
+
+        let (_, first) = list_with_freshness(&backend, "hashicorp").unwrap();
+        let (_, second) = list_with_freshness(&backend, "hashicorp").unwrap();
+
+        assert_eq!(first, None);
+        assert_eq!(second.unwrap().staleness, Staleness::Fresh);
+    }
+
+    #[test]
+    fn stale_listing_is_served_while_refreshing_in_background() {
+        let inner = Arc::new(CountingBackend::default());
+        let backend = caching(
+            &inner,
+            &CacheConfig {
+                stale_while_revalidate_secs: 60,
+                ..config(0, 10)
+            },
+        );
+
+        list(&backend, "hashicorp").unwrap();
+        let (versions, freshness) = list_with_freshness(&backend, "hashicorp").unwrap();
+
+        assert_eq!(versions.len(), 2);
+        assert_eq!(freshness.unwrap().staleness, Staleness::Revalidating);
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while inner.calls() < 2 && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(inner.calls(), 2);
+    }
+
+    #[test]
+    fn stale_listing_is_served_when_upstream_fails() {
+        let inner = Arc::new(CountingBackend::default());
+        let backend = caching(
+            &inner,
+            &CacheConfig {
+                stale_if_error_secs: 60,
+                ..config(0, 10)
+            },
+        );
+
+        list(&backend, "hashicorp").unwrap();
+        inner.failing.store(true, Ordering::SeqCst);
+        let (versions, freshness) = list_with_freshness(&backend, "hashicorp").unwrap();
+
+        assert_eq!(inner.calls(), 2);
+        assert_eq!(versions.len(), 2);
+        assert_eq!(freshness.unwrap().staleness, Staleness::RevalidationFailed);
+    }
+
+    #[test]
+    fn upstream_errors_surface_without_stale_if_error() {
+        let inner = Arc::new(CountingBackend::default());
+        let backend = caching(&inner, &config(0, 10));
+
+        list(&backend, "hashicorp").unwrap();
+        inner.failing.store(true, Ordering::SeqCst);
+
+        assert!(list(&backend, "hashicorp").is_err());
+    }
+}
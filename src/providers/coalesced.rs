@@ -0,0 +1,317 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+
+use crate::index::ProviderRef;
+use crate::params::{Arch, Namespace, Os, ProviderType, Version};
+use crate::types::{Package, ReleaseDiagnostic, VersionInfo};
+
+use super::{Backend, ProviderBackendError, ProviderDetails, Result};
+
+type VersionsKey = (Namespace, ProviderType);
+type PackageKey = (Namespace, ProviderType, Version, Os, Arch);
+
+/// Wraps another backend so that concurrent identical lookups share a single upstream call. The
+/// first caller performs the request; everyone arriving while it is in flight waits for, and
+/// receives, the same result or error.
+///
+/// Waiters block their thread until the leader finishes, so calls must come from blocking
+/// threads rather than async workers; routes reach backends through `call_backend`, which runs
+/// them on the blocking pool.
+pub struct CoalescingBackend {
+    inner: Arc<dyn Backend>,
+    versions: SingleFlight<VersionsKey, Vec<VersionInfo>>,
+    packages: SingleFlight<PackageKey, Package>,
+}
+
+impl CoalescingBackend {
+    pub fn new(inner: Arc<dyn Backend>) -> Self {
+        Self {
+            inner,
+            versions: SingleFlight::default(),
+            packages: SingleFlight::default(),
+        }
+    }
+}
+
+impl Backend for CoalescingBackend {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn health(&self) -> Result<()> {
+        self.inner.health()
+    }
+
+    fn list_provider_versions(
+        &self,
+        namespace: Namespace,
+        provider_type: ProviderType,
+    ) -> Result<Vec<VersionInfo>> {
+        let key = (namespace.clone(), provider_type.clone());
+        self.versions.run(key, || {
+            self.inner.list_provider_versions(namespace, provider_type)
+        })
+    }
+
+    fn find_provider_package(
+        &self,
+        namespace: Namespace,
+        provider_type: ProviderType,
+        version: Version,
+        os: Os,
+        arch: Arch,
+    ) -> Result<Package> {
+        let key = (
+            namespace.clone(),
+            provider_type.clone(),
+            version.clone(),
+            os.clone(),
+            arch.clone(),
+        );
+        self.packages.run(key, || {
+            self.inner
+                .find_provider_package(namespace, provider_type, version, os, arch)
+        })
+    }
+
+    fn diagnose_provider(
+        &self,
+        namespace: Namespace,
+        provider_type: ProviderType,
+    ) -> Result<Vec<ReleaseDiagnostic>> {
+        self.inner.diagnose_provider(namespace, provider_type)
+    }
+
+    fn list_providers(&self) -> Result<Vec<ProviderRef>> {
+        self.inner.list_providers()
+    }
+
+    fn describe_provider(
+        &self,
+        namespace: Namespace,
+        provider_type: ProviderType,
+    ) -> Result<ProviderDetails> {
+        self.inner.describe_provider(namespace, provider_type)
+    }
+
+    fn refresh_provider(&self, namespace: Namespace, provider_type: ProviderType) -> Result<()> {
+        self.inner.refresh_provider(namespace, provider_type)
+    }
+}
+
+struct Flight<V> {
+    result: Mutex<Option<Result<V>>>,
+    done: Condvar,
+}
+
+impl<V> Default for Flight<V> {
+    fn default() -> Self {
+        Self {
+            result: Mutex::new(None),
+            done: Condvar::new(),
+        }
+    }
+}
+
+struct SingleFlight<K, V> {
+    flights: Mutex<HashMap<K, Arc<Flight<V>>>>,
+}
+
+impl<K, V> Default for SingleFlight<K, V> {
+    fn default() -> Self {
+        Self {
+            flights: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> SingleFlight<K, V> {
+    fn run(&self, key: K, call: impl FnOnce() -> Result<V>) -> Result<V> {
+        let (flight, leader) = {
+            let mut flights = self.flights.lock().unwrap_or_else(PoisonError::into_inner);
+            if let Some(flight) = flights.get(&key) {
+                (flight.clone(), false)
+            } else {
+                let flight = Arc::new(Flight::default());
+                flights.insert(key.clone(), flight.clone());
+                (flight, true)
+            }
+        };
+
+        if !leader {
+            let mut result = flight.result.lock().unwrap_or_else(PoisonError::into_inner);
+            loop {
+                if let Some(result) = result.as_ref() {
+                    return result.clone();
+                }
+                result = flight
+                    .done
+                    .wait(result)
+                    .unwrap_or_else(PoisonError::into_inner);
+            }
+        }
+
+        let mut landing = Landing {
+            flights: &self.flights,
+            key,
+            flight,
+            result: None,
+        };
+        let result = call();
+        landing.result = Some(result.clone());
+        result
+    }
+}
+
+/// Publishes the leader's result to waiting callers and retires the flight, even if the upstream
+/// call panicked, in which case waiters receive a `StorageError`.
+struct Landing<'a, K: Eq + Hash, V> {
+    flights: &'a Mutex<HashMap<K, Arc<Flight<V>>>>,
+    key: K,
+    flight: Arc<Flight<V>>,
+    result: Option<Result<V>>,
+}
+
+impl<K: Eq + Hash, V> Drop for Landing<'_, K, V> {
+    fn drop(&mut self) {
+        self.flights
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&self.key);
+
+        let result = self
+            .result
+            .take()
+            .unwrap_or_else(|| Err(ProviderBackendError::storage("the backend call panicked")));
+        *self
+            .flight
+            .result
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Some(result);
+        self.flight.done.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::FakeBackend;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+
+    /// Blocks every call until released, counting how many calls reached it.
+    struct GatedBackend {
+        calls: AtomicUsize,
+        release: Mutex<mpsc::Receiver<()>>,
+        fail: bool,
+    }
+
+    impl Backend for GatedBackend {
+        fn name(&self) -> &'static str {
+            "gated"
+        }
+
+        fn health(&self) -> Result<()> {
+            Ok(())
+        }
+
+        fn list_provider_versions(
+            &self,
+            namespace: Namespace,
+            provider_type: ProviderType,
+        ) -> Result<Vec<VersionInfo>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            self.release.lock().unwrap().recv().unwrap();
+            if self.fail {
+                return Err(ProviderBackendError::NotFound);
+            }
+            FakeBackend.list_provider_versions(namespace, provider_type)
+        }
+
+        fn find_provider_package(
+            &self,
+            _namespace: Namespace,
+            _provider_type: ProviderType,
+            _version: Version,
+            _os: Os,
+            _arch: Arch,
+        ) -> Result<Package> {
+            Err(ProviderBackendError::NotFound)
+        }
+    }
+
+    fn in_flight_callers(backend: &CoalescingBackend) -> usize {
+        backend
+            .versions
+            .flights
+            .lock()
+            .unwrap()
+            .values()
+            .map(|flight| Arc::strong_count(flight) - 1)
+            .sum()
+    }
+
+    fn run_concurrently(fail: bool, callers: usize) -> (usize, Vec<Result<Vec<VersionInfo>>>) {
+        let (release, receiver) = mpsc::channel();
+        let inner = Arc::new(GatedBackend {
+            calls: AtomicUsize::new(0),
+            release: Mutex::new(receiver),
+            fail,
+        });
+        let backend = Arc::new(CoalescingBackend::new(inner.clone()));
+
+        let handles: Vec<_> = (0..callers)
+            .map(|_| {
+                let backend = backend.clone();
+                thread::spawn(move || {
+                    backend.list_provider_versions("acme".parse().unwrap(), "foo".parse().unwrap())
+                })
+            })
+            .collect();
+
+        while in_flight_callers(&backend) < callers {
+            thread::sleep(Duration::from_millis(1));
+        }
+        release.send(()).unwrap();
+
+        let results = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        (inner.calls.load(Ordering::SeqCst), results)
+    }
+
+    #[test]
+    fn concurrent_identical_lookups_share_one_upstream_call() {
+        let (calls, results) = run_concurrently(false, 8);
+
+        assert_eq!(calls, 1);
+        assert!(results.iter().all(|r| r.as_ref().unwrap().len() == 2));
+    }
+
+    #[test]
+    fn concurrent_identical_lookups_share_errors() {
+        let (calls, results) = run_concurrently(true, 8);
+
+        assert_eq!(calls, 1);
+        assert!(
+            results
+                .iter()
+                .all(|r| matches!(r, Err(ProviderBackendError::NotFound)))
+        );
+    }
+
+    #[test]
+    fn sequential_lookups_are_not_coalesced() {
+        let backend = CoalescingBackend::new(Arc::new(FakeBackend));
+
+        backend
+            .list_provider_versions("acme".parse().unwrap(), "foo".parse().unwrap())
+            .unwrap();
+        backend
+            .list_provider_versions("acme".parse().unwrap(), "foo".parse().unwrap())
+            .unwrap();
+
+        assert_eq!(in_flight_callers(&backend), 0);
+    }
+}
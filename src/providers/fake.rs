@@ -1,3 +1,4 @@
+use crate::params::{Arch, Namespace, Os, ProviderType, Version};
 use crate::types::{GpgPublicKey, Package, Platform, SigningKeys, VersionInfo};
 
 use super::{Backend, Result};
@@ -6,10 +7,18 @@ use super::{Backend, Result};
 pub struct FakeBackend;
 
 impl Backend for FakeBackend {
+    fn name(&self) -> &'static str {
+        "fake"
+    }
+
+    fn health(&self) -> Result<()> {
+        Ok(())
+    }
+
     fn list_provider_versions(
         &self,
-        _: String,
-        _provider_type: String,
+        _: Namespace,
+        _provider_type: ProviderType,
     ) -> Result<Vec<VersionInfo>> {
         Ok(vec![
             VersionInfo {
@@ -37,6 +46,7 @@ impl Backend for FakeBackend {
                         arch: "amd64".to_string(),
                     },
                 ],
+                deprecation: None,
             },
             VersionInfo {
                 version: "0.9.0".to_string(),
@@ -45,25 +55,26 @@ impl Backend for FakeBackend {
                     os: "linux".to_string(),
                     arch: "amd64".to_string(),
                 }],
+                deprecation: None,
             },
         ])
     }
 
     fn find_provider_package(
         &self,
-        namespace: String,
-        provider_type: String,
-        version: String,
-        os: String,
-        arch: String,
+        namespace: Namespace,
+        provider_type: ProviderType,
+        version: Version,
+        os: Os,
+        arch: Arch,
     ) -> Result<Package> {
         // Stub response with example download data
         let filename = format!("terraform-provider-{provider_type}_{version}_{os}_{arch}.zip");
 
         Ok(Package {
             protocols: vec!["5.0".to_string()],
-            os: os.clone(),
-            arch: arch.clone(),
+            os: os.into(),
+            arch: arch.into(),
             filename: filename.clone(),
             download_url: format!(
                 "https://releases.example.com/{namespace}/{provider_type}/{filename}"
@@ -0,0 +1,124 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Component, Path, PathBuf};
+
+use crate::config::FilesystemConfig;
+
+use super::{ObjectStore, ProviderBackendError, Result};
+
+/// Suffix of the temporary files objects are written to before being renamed into place. They
+/// are never served, and no key may end with it.
+pub const PARTIAL_SUFFIX: &str = ".partial";
+
+/// Stores objects as files below a root directory. The registry serves that directory at
+/// `/files`, so `base_url` normally points there.
+pub struct FilesystemStore {
+    root: PathBuf,
+    base_url: String,
+}
+
+impl FilesystemStore {
+    pub fn new(cfg: FilesystemConfig) -> Self {
+        Self {
+            root: cfg.root,
+            base_url: cfg.base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Maps a key to a path below the root, refusing keys that would escape it or clash with a
+    /// temporary file.
+    fn path(&self, key: &str) -> Result<PathBuf> {
+        let relative = Path::new(key);
+        if key.ends_with(PARTIAL_SUFFIX)
+            || !relative
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)))
+        {
+            return Err(ProviderBackendError::NotFound);
+        }
+        Ok(self.root.join(relative))
+    }
+}
+
+impl ObjectStore for FilesystemStore {
+    fn name(&self) -> &'static str {
+        "filesystem"
+    }
+
+    fn health(&self) -> Result<()> {
+        match fs::metadata(&self.root) {
+            Ok(metadata) if metadata.is_dir() => Ok(()),
+            Ok(_) => Err(ProviderBackendError::storage(format!(
+                "`{}` is not a directory",
+                self.root.display()
+            ))),
+            Err(error) => Err(ProviderBackendError::storage(error)),
+        }
+    }
+
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        match fs::read(self.path(key)?) {
+            Ok(contents) => Ok(Some(contents)),
+            Err(error) if error.kind() == ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(ProviderBackendError::storage(error)),
+        }
+    }
+
+    /// Writes through a temporary file and renames it into place, so readers never see a
+    /// partially written object.
+    fn put(&self, key: &str, contents: &[u8]) -> Result<()> {
+        let path = self.path(key)?;
+        let mut partial = path.clone().into_os_string();
+        partial.push(PARTIAL_SUFFIX);
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(ProviderBackendError::storage)?;
+        }
+        fs::write(&partial, contents).map_err(ProviderBackendError::storage)?;
+        fs::rename(&partial, &path).map_err(ProviderBackendError::storage)
+    }
+
+    fn url(&self, key: &str) -> String {
+        format!("{}/{key}", self.base_url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store(root: &Path) -> FilesystemStore {
+        FilesystemStore::new(FilesystemConfig {
+            root: root.to_path_buf(),
+            base_url: "https://registry.example.com/files/".to_string(),
+        })
+    }
+
+    #[test]
+    fn round_trips_objects() {
+        let root = tempfile::tempdir().unwrap();
+        let store = store(root.path());
+
+        store.put("acme/foo/versions.json", b"[]").unwrap();
+
+        assert_eq!(
+            store.get("acme/foo/versions.json").unwrap(),
+            Some(b"[]".to_vec())
+        );
+        assert_eq!(store.get("acme/bar/versions.json").unwrap(), None);
+        assert_eq!(
+            store.url("acme/foo/versions.json"),
+            "https://registry.example.com/files/acme/foo/versions.json"
+        );
+    }
+
+    #[test]
+    fn refuses_keys_outside_root() {
+        let root = tempfile::tempdir().unwrap();
+        let store = store(&root.path().join("store"));
+
+        assert!(store.put("../escaped", b"x").is_err());
+        assert!(store.get("/etc/passwd").is_err());
+        assert!(store.put("acme/foo/file.zip.partial", b"x").is_err());
+    }
+}
@@ -1,212 +1,765 @@
-use crate::types::{Package, Platform, VersionInfo};
-use std::str::FromStr;
-use std::sync::Arc;
+use crate::config::PlatformsConfig;
+use crate::index::ProviderRef;
+use crate::params::{Arch, Namespace, Os, ProviderType, Version};
+use crate::types::{
+    AssetDiagnostic, GpgPublicKey, Package, Platform, ReleaseDiagnostic, SigningKeys, VersionInfo,
+};
+use regex::Regex;
+use reqwest::Url;
+use reqwest::blocking::Client;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, LazyLock};
 
-use super::{Backend, ProviderBackendError, Result};
-use crate::providers::ProviderBackendError::StorageError;
+use super::{Backend, ProviderBackendError, ProviderDetails, Result};
 use crate::providers::gitlabrelease::TryFromLinkForPlatformError::{
     InvalidFileNameFormat, UnsupportedArch, UnsupportedOS,
 };
-use gitlab::Gitlab;
-use gitlab::api::Query;
+use gitlab::api::projects::Project;
 use gitlab::api::projects::releases::ProjectReleases;
+use gitlab::api::users::CurrentUser;
+use gitlab::api::{ApiError, Query};
+use gitlab::{Gitlab, RestError};
 use serde_derive::Deserialize;
 use serde_derive::Serialize;
+use thiserror::Error;
+use tracing::{instrument, warn};
 
 #[derive(Clone)]
-#[allow(dead_code)]
 pub struct GitLabBackend {
     client: Arc<Gitlab>,
+    /// Fetches release assets, which live outside the GitLab API.
+    http: Client,
+    host: String,
+    token: String,
     project: Option<String>,
+    /// Keyed by namespace and provider type. Providers without a pattern use the default `v{semver}`.
+    tag_patterns: HashMap<(String, String), TagPattern>,
+    asset_template: AssetTemplate,
+    platforms: PlatformsConfig,
+    signing_keys: Vec<GpgPublicKey>,
+}
+
+/// A regular expression matching the release tags of one provider, whose single capture group is
+/// the version. Tags that do not match belong to some other provider.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct TagPattern(Regex);
+
+impl TagPattern {
+    /// Matches `v{semver}` tags, as used by repositories holding a single provider.
+    const DEFAULT: &'static str = "^v(.+)$";
+
+    pub fn new(pattern: &str) -> std::result::Result<Self, String> {
+        let regex = Regex::new(pattern).map_err(|error| error.to_string())?;
+        if regex.captures_len() != 2 {
+            return Err(format!(
+                "tag pattern `{pattern}` must have exactly one capture group for the version"
+            ));
+        }
+        Ok(Self(regex))
+    }
+
+    fn version<'t>(&self, tag: &'t str) -> Option<&'t str> {
+        self.0
+            .captures(tag)
+            .and_then(|captures| captures.get(1))
+            .map(|version| version.as_str())
+    }
+
+    fn expectation(&self) -> String {
+        if self.0.as_str() == Self::DEFAULT {
+            "tag name must be in the format `v{semver}`, i.e. v1.0.3".to_string()
+        } else {
+            format!("tag name must match `{}`", self.0.as_str())
+        }
+    }
+}
+
+static DEFAULT_TAG_PATTERN: LazyLock<TagPattern> = LazyLock::new(TagPattern::default);
+
+impl Default for TagPattern {
+    fn default() -> Self {
+        Self(Regex::new(Self::DEFAULT).unwrap_or_else(|_| unreachable!()))
+    }
+}
+
+impl PartialEq for TagPattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.as_str() == other.0.as_str()
+    }
+}
+
+impl TryFrom<String> for TagPattern {
+    type Error = String;
+
+    fn try_from(pattern: String) -> std::result::Result<Self, Self::Error> {
+        Self::new(&pattern)
+    }
+}
+
+impl From<TagPattern> for String {
+    fn from(pattern: TagPattern) -> Self {
+        pattern.0.as_str().to_string()
+    }
+}
+
+/// How package assets are named, with `{type}`, `{version}`, `{os}` and `{arch}` placeholders.
+/// `{os}` and `{arch}` must each appear exactly once.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct AssetTemplate {
+    template: String,
+    segments: Vec<AssetSegment>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum AssetSegment {
+    Literal(String),
+    Type,
+    Version,
+    Os,
+    Arch,
+}
+
+impl AssetTemplate {
+    /// The name goreleaser gives packages in the provider scaffolding published by `HashiCorp`.
+    const DEFAULT: &'static str = "terraform-provider-{type}_{version}_{os}_{arch}.zip";
+
+    pub fn new(template: &str) -> std::result::Result<Self, String> {
+        let mut segments = Vec::new();
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                segments.push(AssetSegment::Literal(rest[..start].to_string()));
+            }
+            let end = rest[start..]
+                .find('}')
+                .ok_or_else(|| format!("unclosed placeholder in asset template `{template}`"))?;
+            segments.push(match &rest[start + 1..start + end] {
+                "type" => AssetSegment::Type,
+                "version" => AssetSegment::Version,
+                "os" => AssetSegment::Os,
+                "arch" => AssetSegment::Arch,
+                other => {
+                    return Err(format!(
+                        "unknown placeholder `{{{other}}}` in asset template `{template}`"
+                    ));
+                }
+            });
+            rest = &rest[start + end + 1..];
+        }
+        if !rest.is_empty() {
+            segments.push(AssetSegment::Literal(rest.to_string()));
+        }
+
+        for (placeholder, segment) in [("{os}", AssetSegment::Os), ("{arch}", AssetSegment::Arch)] {
+            if segments.iter().filter(|s| **s == segment).count() != 1 {
+                return Err(format!(
+                    "asset template `{template}` must contain `{placeholder}` exactly once"
+                ));
+            }
+        }
+
+        Ok(Self {
+            template: template.to_string(),
+            segments,
+        })
+    }
+
+    /// Builds a matcher for the assets of one release. Without a `provider_type`, `{type}` matches
+    /// any name; the version still anchors the match, so names may contain the separator.
+    fn matcher(
+        &self,
+        provider_type: Option<&str>,
+        version: &str,
+    ) -> std::result::Result<AssetMatcher, regex::Error> {
+        let mut pattern = String::from("^");
+        for segment in &self.segments {
+            match segment {
+                AssetSegment::Literal(literal) => pattern.push_str(&regex::escape(literal)),
+                AssetSegment::Type => match provider_type {
+                    Some(provider_type) => pattern.push_str(&regex::escape(provider_type)),
+                    None => pattern.push_str(".+?"),
+                },
+                AssetSegment::Version => pattern.push_str(&regex::escape(version)),
+                AssetSegment::Os => pattern.push_str("(?P<os>[a-z0-9]+)"),
+                AssetSegment::Arch => pattern.push_str("(?P<arch>[a-z0-9]+)"),
+            }
+        }
+        pattern.push('$');
+
+        Regex::new(&pattern).map(AssetMatcher)
+    }
+}
+
+impl Default for AssetTemplate {
+    fn default() -> Self {
+        DEFAULT_ASSET_TEMPLATE.clone()
+    }
+}
+
+static DEFAULT_ASSET_TEMPLATE: LazyLock<AssetTemplate> =
+    LazyLock::new(|| AssetTemplate::new(AssetTemplate::DEFAULT).unwrap_or_else(|_| unreachable!()));
+
+impl TryFrom<String> for AssetTemplate {
+    type Error = String;
+
+    fn try_from(template: String) -> std::result::Result<Self, Self::Error> {
+        Self::new(&template)
+    }
+}
+
+impl From<AssetTemplate> for String {
+    fn from(template: AssetTemplate) -> Self {
+        template.template
+    }
 }
 
+/// An `AssetTemplate` filled in for one release.
+struct AssetMatcher(Regex);
+
+impl AssetMatcher {
+    fn platform(
+        &self,
+        link: &Link,
+        platforms: &PlatformsConfig,
+    ) -> std::result::Result<Platform, TryFromLinkForPlatformError> {
+        let captures = self.0.captures(&link.name).ok_or(InvalidFileNameFormat)?;
+        let os = &captures["os"];
+        let arch = &captures["arch"];
+
+        if !platforms.accepts_os(os) {
+            return Err(UnsupportedOS(os.to_string()));
+        }
+        if !platforms.accepts_arch(arch) {
+            return Err(UnsupportedArch(arch.to_string()));
+        }
+
+        Ok(Platform {
+            os: os.to_string(),
+            arch: arch.to_string(),
+        })
+    }
+}
+
+static DEFAULT_PLATFORMS: LazyLock<PlatformsConfig> = LazyLock::new(PlatformsConfig::default);
+
+/// How the releases and assets of one provider are named, and which of their platforms are
+/// accepted.
+pub struct ReleaseNaming<'a> {
+    /// When known, only assets of this provider are read.
+    pub provider_type: Option<&'a str>,
+    pub tags: &'a TagPattern,
+    pub assets: &'a AssetTemplate,
+    pub platforms: &'a PlatformsConfig,
+}
+
+impl Default for ReleaseNaming<'_> {
+    fn default() -> Self {
+        Self {
+            provider_type: None,
+            tags: &DEFAULT_TAG_PATTERN,
+            assets: &DEFAULT_ASSET_TEMPLATE,
+            platforms: &DEFAULT_PLATFORMS,
+        }
+    }
+}
+
+/// Why project lookups fail when no project is configured.
+const NO_PROJECT: &str = "no GitLab project is configured";
+
 impl Backend for GitLabBackend {
+    fn name(&self) -> &'static str {
+        "gitlab_release"
+    }
+
+    fn health(&self) -> Result<()> {
+        self.check_access()
+    }
+
     fn list_provider_versions(
         &self,
-        _namespace: String,
-        _provider_type: String,
+        namespace: Namespace,
+        provider_type: ProviderType,
     ) -> Result<Vec<VersionInfo>> {
+        let naming = self.naming(&namespace, &provider_type);
+
         if let Some(project) = &self.project {
             return match self.list_project_releases(project) {
                 Ok(releases) => Ok(releases
                     .iter()
-                    .filter_map(|rel| VersionInfo::try_from(rel).ok())
+                    .filter_map(|rel| {
+                        let parsed = ParsedRelease::try_from((rel, &naming)).ok()?;
+                        for rejected in &parsed.rejected {
+                            warn!(
+                                %namespace,
+                                %provider_type,
+                                tag = %rel.tag_name,
+                                asset = %rejected.name,
+                                "Ignoring release asset: {}",
+                                rejected.reason
+                            );
+                        }
+                        Some(parsed.version)
+                    })
                     .collect()),
-                Err(ProviderBackendError::NotFound) => Err(ProviderBackendError::NotFound),
-                Err(ProviderBackendError::StorageError) => Err(ProviderBackendError::StorageError),
+                Err(error) => Err(error),
             };
         }
 
-        Err(ProviderBackendError::StorageError)
+        Err(ProviderBackendError::storage(NO_PROJECT))
     }
 
     fn find_provider_package(
         &self,
-        _namespace: String,
-        _provider_type: String,
-        _version: String,
-        _os: String,
-        _arch: String,
+        namespace: Namespace,
+        provider_type: ProviderType,
+        version: Version,
+        os: Os,
+        arch: Arch,
     ) -> Result<Package> {
-        todo!()
+        let naming = self.naming(&namespace, &provider_type);
+        let project = self
+            .project
+            .as_deref()
+            .ok_or_else(|| ProviderBackendError::storage(NO_PROJECT))?;
+        let release = self
+            .list_project_releases(project)?
+            .iter()
+            .filter_map(|release| ParsedRelease::try_from((release, &naming)).ok())
+            .find(|parsed| parsed.version.version == *version)
+            .ok_or(ProviderBackendError::NotFound)?;
+        let package = release.package(&os, &arch).ok_or_else(|| {
+            ProviderBackendError::PlatformUnsupported {
+                os: os.to_string(),
+                arch: arch.to_string(),
+            }
+        })?;
+
+        let sums = self.download_text(&release.shasums.direct_asset_url)?;
+        let Some(shasum) = shasum_for(&sums, &package.name) else {
+            warn!(
+                %namespace,
+                %provider_type,
+                %version,
+                asset = %package.name,
+                "Checksum file `{}` has no entry for the package",
+                release.shasums.name
+            );
+            return Err(ProviderBackendError::storage(format!(
+                "`{}` has no entry for `{}`",
+                release.shasums.name, package.name
+            )));
+        };
+
+        Ok(Package {
+            protocols: release.version.protocols.clone(),
+            os: os.into(),
+            arch: arch.into(),
+            filename: package.name.clone(),
+            download_url: package.direct_asset_url.clone(),
+            shasums_url: release.shasums.direct_asset_url.clone(),
+            shasums_signature_url: release.shasums_signature.direct_asset_url.clone(),
+            shasum,
+            signing_keys: SigningKeys {
+                gpg_public_keys: self.signing_keys.clone(),
+            },
+        })
+    }
+
+    fn diagnose_provider(
+        &self,
+        namespace: Namespace,
+        provider_type: ProviderType,
+    ) -> Result<Vec<ReleaseDiagnostic>> {
+        let naming = self.naming(&namespace, &provider_type);
+        let project = self
+            .project
+            .as_deref()
+            .ok_or_else(|| ProviderBackendError::storage(NO_PROJECT))?;
+
+        Ok(self
+            .list_project_releases(project)?
+            .iter()
+            .map(|release| diagnose_release(release, &naming))
+            .collect())
+    }
+
+    /// Only providers with a configured tag pattern are known by name; the default pattern
+    /// answers for any provider asked about.
+    fn list_providers(&self) -> Result<Vec<ProviderRef>> {
+        Ok(self
+            .tag_patterns
+            .keys()
+            .filter_map(|(namespace, provider_type)| {
+                Some(ProviderRef {
+                    namespace: namespace.parse().ok()?,
+                    provider_type: provider_type.parse().ok()?,
+                })
+            })
+            .collect())
+    }
+
+    fn describe_provider(
+        &self,
+        namespace: Namespace,
+        provider_type: ProviderType,
+    ) -> Result<ProviderDetails> {
+        let naming = self.naming(&namespace, &provider_type);
+        let project = self
+            .project
+            .as_deref()
+            .ok_or_else(|| ProviderBackendError::storage(NO_PROJECT))?;
+        let details = self.project_details(project)?;
+
+        Ok(ProviderDetails {
+            description: details.description.filter(|d| !d.is_empty()),
+            source: Some(details.web_url),
+            tags: details.topics,
+            published: published_versions(&self.list_project_releases(project)?, &naming),
+        })
     }
 }
 
 impl GitLabBackend {
-    pub fn new(cfg: crate::config::GitLabConfig) -> Result<Self> {
-        let client = Gitlab::new(cfg.host, cfg.token).map_err(|_| StorageError)?;
+    pub fn new(cfg: crate::config::GitLabConfig, platforms: PlatformsConfig) -> Result<Self> {
+        let client = Gitlab::new(&cfg.host, &cfg.token).map_err(ProviderBackendError::storage)?;
 
         Ok(Self {
             client: Arc::new(client),
+            http: Client::new(),
+            host: cfg.host,
+            token: cfg.token,
             project: cfg.project,
+            tag_patterns: cfg
+                .tag_patterns
+                .into_iter()
+                .map(|tags| ((tags.namespace, tags.provider_type), tags.pattern))
+                .collect(),
+            asset_template: cfg.asset_template,
+            platforms,
+            signing_keys: cfg.signing_keys,
         })
     }
 
-    fn list_project_releases(&self, project: &str) -> Result<Vec<GitLabRelease>> {
-        let endpoint = ProjectReleases::builder()
-            .project(urlencoding::encode(project).to_string())
-            .build()
-            .map_err(|_| ProviderBackendError::StorageError)?;
+    fn naming<'a>(&'a self, namespace: &str, provider_type: &'a str) -> ReleaseNaming<'a> {
+        ReleaseNaming {
+            provider_type: Some(provider_type),
+            tags: self
+                .tag_patterns
+                .get(&(namespace.to_string(), provider_type.to_string()))
+                .unwrap_or(&DEFAULT_TAG_PATTERN),
+            assets: &self.asset_template,
+            platforms: &self.platforms,
+        }
+    }
+
+    /// Reading the project (or the token's user when no project is configured) proves both that
+    /// GitLab is reachable and that the token is still valid.
+    #[instrument(skip(self), fields(otel.kind = "client"), err)]
+    fn check_access(&self) -> Result<()> {
+        let result = match &self.project {
+            Some(project) => gitlab::api::ignore(project_endpoint(project)?).query(&*self.client),
+            None => gitlab::api::ignore(
+                CurrentUser::builder()
+                    .build()
+                    .map_err(ProviderBackendError::storage)?,
+            )
+            .query(&*self.client),
+        };
+
+        result.map_err(api_error)
+    }
+
+    /// Fetches a release asset, authenticating only to the GitLab host itself so the token is
+    /// never sent to wherever else an asset link points.
+    #[instrument(skip(self), fields(otel.kind = "client"), err)]
+    fn download_text(&self, url: &str) -> Result<String> {
+        let mut request = self.http.get(url);
+        if Url::parse(url).is_ok_and(|url| url.host_str() == Some(self.host.as_str())) {
+            request = request.header("PRIVATE-TOKEN", &self.token);
+        }
 
-        let releases: Vec<GitLabRelease> = endpoint
+        request
+            .send()
+            .and_then(reqwest::blocking::Response::error_for_status)
+            .and_then(reqwest::blocking::Response::text)
+            .map_err(ProviderBackendError::from_request)
+    }
+
+    #[instrument(skip(self), fields(otel.kind = "client"), err)]
+    fn project_details(&self, project: &str) -> Result<GitLabProject> {
+        project_endpoint(project)?
             .query(&*self.client)
-            .map_err(|_| ProviderBackendError::StorageError)?;
+            .map_err(api_error)
+    }
+
+    #[instrument(skip(self), fields(otel.kind = "client"), err)]
+    fn list_project_releases(&self, project: &str) -> Result<Vec<GitLabRelease>> {
+        let releases: Vec<GitLabRelease> = releases_endpoint(project)?
+            .query(&*self.client)
+            .map_err(api_error)?;
 
         Ok(releases)
     }
 }
 
-#[derive(Debug)]
-#[allow(dead_code)]
+/// The endpoints take the project path as configured, e.g. `group/project`, and percent-encode
+/// it themselves; encoding it beforehand would address `group%252Fproject`.
+fn project_endpoint(project: &str) -> Result<Project<'_>> {
+    Project::builder()
+        .project(project)
+        .build()
+        .map_err(ProviderBackendError::storage)
+}
+
+fn releases_endpoint(project: &str) -> Result<ProjectReleases<'_>> {
+    ProjectReleases::builder()
+        .project(project)
+        .build()
+        .map_err(ProviderBackendError::storage)
+}
+
+/// Keeps what GitLab said went wrong, so rejected tokens, rate limits and timeouts reach clients
+/// as such rather than as a generic storage error.
+fn api_error(error: ApiError<RestError>) -> ProviderBackendError {
+    match error {
+        ApiError::GitlabRateLimited { retry_after, .. } => ProviderBackendError::RateLimited {
+            retry_after: Some(retry_after),
+            cause: Arc::new(error),
+        },
+        ApiError::Auth { .. } => ProviderBackendError::Unauthorized(Arc::new(error)),
+        ApiError::Client {
+            source: RestError::Communication { source },
+        } => ProviderBackendError::from_request(source),
+        ApiError::GitlabService { status, .. }
+        | ApiError::GitlabWithStatus { status, .. }
+        | ApiError::GitlabObjectWithStatus { status, .. }
+        | ApiError::GitlabUnrecognizedWithStatus { status, .. } => {
+            ProviderBackendError::from_status("gitlab", status.as_u16(), None)
+        }
+        _ => ProviderBackendError::storage(error),
+    }
+}
+
+#[derive(Error, Debug)]
 pub enum TryFromGitLabError {
+    #[error("tag `{0}` is not a version: {1}")]
     InvalidVersion(String, String),
+    #[error("no checksum signature asset (`*SUMS.sig`)")]
     MissingSignatureLink,
+    #[error("no checksum asset (`*SUMS`)")]
     MissingShaSumsLink,
-    InvalidPackageLink(TryFromLinkForPlatformError),
+    #[error("asset template cannot be filled in for this version")]
+    InvalidTemplate,
 }
 
 impl TryFrom<&GitLabRelease> for VersionInfo {
     type Error = TryFromGitLabError;
 
     fn try_from(value: &GitLabRelease) -> std::result::Result<Self, Self::Error> {
-        let mut tag_name = value.tag_name.clone();
-        if !value.tag_name.starts_with('v') {
-            return Err(TryFromGitLabError::InvalidVersion(
-                value.tag_name.clone(),
-                "tag name must be in the format `v{semver}`, i.e. v1.0.3".to_string(),
-            ));
-        }
+        Self::try_from((value, &ReleaseNaming::default()))
+    }
+}
 
-        let tag_end = tag_name.split_off(1);
+impl TryFrom<(&GitLabRelease, &ReleaseNaming<'_>)> for VersionInfo {
+    type Error = TryFromGitLabError;
+
+    fn try_from(
+        (value, naming): (&GitLabRelease, &ReleaseNaming<'_>),
+    ) -> std::result::Result<Self, Self::Error> {
+        ParsedRelease::try_from((value, naming)).map(|parsed| parsed.version)
+    }
+}
+
+/// A release read as a provider version, along with the package assets that were left out of it.
+pub struct ParsedRelease {
+    pub version: VersionInfo,
+    /// The package asset of each platform in `version.platforms`, in the same order.
+    pub packages: Vec<Link>,
+    pub shasums: Link,
+    pub shasums_signature: Link,
+    pub rejected: Vec<RejectedAsset>,
+}
+
+impl ParsedRelease {
+    fn package(&self, os: &str, arch: &str) -> Option<&Link> {
+        self.version
+            .platforms
+            .iter()
+            .zip(&self.packages)
+            .find(|(platform, _)| platform.os == os && platform.arch == arch)
+            .map(|(_, link)| link)
+    }
+}
 
-        let version = semver::Version::parse(&tag_end).map_err(|_| {
+/// Reads the checksum of `filename` from a `SHA256SUMS` file, as written by `sha256sum`.
+fn shasum_for(sums: &str, filename: &str) -> Option<String> {
+    sums.lines().find_map(|line| {
+        let (shasum, name) = line.split_once(char::is_whitespace)?;
+        (name.trim_start().trim_start_matches('*') == filename).then(|| shasum.to_string())
+    })
+}
+
+pub struct RejectedAsset {
+    pub name: String,
+    pub reason: TryFromLinkForPlatformError,
+}
+
+/// Reads a release as a version of the provider named as described.
+impl TryFrom<(&GitLabRelease, &ReleaseNaming<'_>)> for ParsedRelease {
+    type Error = TryFromGitLabError;
+
+    fn try_from(
+        (value, naming): (&GitLabRelease, &ReleaseNaming<'_>),
+    ) -> std::result::Result<Self, Self::Error> {
+        let tag_end = naming.tags.version(&value.tag_name).ok_or_else(|| {
+            TryFromGitLabError::InvalidVersion(value.tag_name.clone(), naming.tags.expectation())
+        })?;
+
+        let version = semver::Version::parse(tag_end).map_err(|_| {
             TryFromGitLabError::InvalidVersion(
                 value.tag_name.clone(),
                 "tag name must be a valid semantic version".to_string(),
             )
         })?;
 
-        value
+        let shasums_signature = value
             .assets
             .links
             .iter()
             .find(|link| link.name.ends_with("SUMS.sig"))
-            .ok_or(TryFromGitLabError::MissingSignatureLink)?;
-        value
+            .ok_or(TryFromGitLabError::MissingSignatureLink)?
+            .clone();
+        let shasums = value
             .assets
             .links
             .iter()
             .find(|link| link.name.ends_with("SUMS"))
-            .ok_or(TryFromGitLabError::MissingShaSumsLink)?;
+            .ok_or(TryFromGitLabError::MissingShaSumsLink)?
+            .clone();
 
         let zip_file_urls: Vec<Link> = value
             .assets
             .links
             .iter()
-            .filter(|l| {
-                std::path::Path::new(&l.name)
-                    .extension()
-                    .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
-            })
+            .filter(|l| is_zip(&l.name))
             .cloned()
             .collect();
 
-        // Gather the platforms Iterate through the file names, parse their platforms, and discard cases where parsing fails.
-        let platforms: Vec<Platform> = zip_file_urls
-            .iter()
-            .map(|x| Platform::try_from(x.clone()))
-            .filter_map(std::result::Result::ok)
-            .collect();
+        // Parse the platform of each package, keeping the ones that fail so they can be reported.
+        let matcher = naming
+            .assets
+            .matcher(naming.provider_type, &version.to_string())
+            .map_err(|_| TryFromGitLabError::InvalidTemplate)?;
+        let mut platforms = Vec::new();
+        let mut packages = Vec::new();
+        let mut rejected = Vec::new();
+        for link in &zip_file_urls {
+            match matcher.platform(link, naming.platforms) {
+                Ok(platform) => {
+                    platforms.push(platform);
+                    packages.push(link.clone());
+                }
+                Err(reason) => rejected.push(RejectedAsset {
+                    name: link.name.clone(),
+                    reason,
+                }),
+            }
+        }
 
         Ok(Self {
-            version: version.to_string(),
-            protocols: vec!["5.0".to_string()],
-            platforms,
+            version: VersionInfo {
+                version: version.to_string(),
+                protocols: vec!["5.0".to_string()],
+                platforms,
+                deprecation: None,
+            },
+            packages,
+            shasums,
+            shasums_signature,
+            rejected,
         })
     }
 }
 
-#[derive(Debug)]
-#[allow(dead_code)]
-pub enum TryFromLinkForPlatformError {
-    NotZipFile,
-    InvalidFileNameFormat,
-    UnsupportedOS(String),
-    UnsupportedArch(String),
-}
-
-impl TryFrom<Link> for Platform {
-    type Error = TryFromLinkForPlatformError;
-
-    fn try_from(link: Link) -> std::result::Result<Self, Self::Error> {
-        let without_extension = link
-            .name
-            .strip_suffix(".zip")
-            .ok_or(TryFromLinkForPlatformError::NotZipFile)?;
-
-        let segments: Vec<&str> = without_extension.split('_').collect();
-        if segments.len() != 3 {
-            return Err(InvalidFileNameFormat);
-        }
-
-        let os = SupportedOS::from_str(segments[1])
-            .map_err(|_| UnsupportedOS(segments[1].to_string()))?;
-        let arch = SupportedArch::from_str(segments[2])
-            .map_err(|_| UnsupportedArch(segments[2].to_string()))?;
-
-        Ok(Self {
-            os: os.0,
-            arch: arch.0,
+/// When each release that reads as a version of the provider was published.
+fn published_versions(
+    releases: &[GitLabRelease],
+    naming: &ReleaseNaming<'_>,
+) -> BTreeMap<String, String> {
+    releases
+        .iter()
+        .filter_map(|release| {
+            let released_at = release.released_at.clone()?;
+            let version = VersionInfo::try_from((release, naming)).ok()?;
+            Some((version.version, released_at))
         })
-    }
+        .collect()
 }
 
-struct SupportedOS(String);
-
-impl FromStr for SupportedOS {
-    type Err = String;
+fn is_zip(name: &str) -> bool {
+    std::path::Path::new(name)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+}
 
-    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
-        match s {
-            "linux" | "darwin" | "windows" | "freebsd" | "openbsd" | "solaris" => {
-                Ok(SupportedOS(s.to_string()))
+/// Explains how a release was read: the version it is served as or why it was skipped, and what
+/// became of each of its assets.
+fn diagnose_release(release: &GitLabRelease, naming: &ReleaseNaming<'_>) -> ReleaseDiagnostic {
+    let parsed = ParsedRelease::try_from((release, naming));
+    let assets = release
+        .assets
+        .links
+        .iter()
+        .map(|link| {
+            let reason = match &parsed {
+                Err(_) => Some("release was skipped".to_string()),
+                Ok(parsed) => match parsed.rejected.iter().find(|r| r.name == link.name) {
+                    Some(rejected) => Some(rejected.reason.to_string()),
+                    None if is_zip(&link.name)
+                        || link.name.ends_with("SUMS")
+                        || link.name.ends_with("SUMS.sig") =>
+                    {
+                        None
+                    }
+                    None => Some("not a package, checksum or signature file".to_string()),
+                },
+            };
+            AssetDiagnostic {
+                name: link.name.clone(),
+                accepted: reason.is_none(),
+                reason,
             }
-            _ => Err(format!("unsupported os: {s}")),
-        }
+        })
+        .collect();
+
+    match parsed {
+        Ok(parsed) => ReleaseDiagnostic {
+            tag: release.tag_name.clone(),
+            version: Some(parsed.version.version),
+            accepted: true,
+            reason: None,
+            assets,
+        },
+        Err(error) => ReleaseDiagnostic {
+            tag: release.tag_name.clone(),
+            version: None,
+            accepted: false,
+            reason: Some(error.to_string()),
+            assets,
+        },
     }
 }
 
-struct SupportedArch(String);
-
-impl FromStr for SupportedArch {
-    type Err = String;
-
-    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
-        match s {
-            "arm" | "arm64" | "386" | "amd64" => Ok(SupportedArch(s.to_string())),
-            _ => Err(format!("unsupported architecture: {s}")),
-        }
-    }
+#[derive(Error, Debug)]
+pub enum TryFromLinkForPlatformError {
+    #[error("name does not match the asset template")]
+    InvalidFileNameFormat,
+    #[error("operating system `{0}` is not accepted")]
+    UnsupportedOS(String),
+    #[error("architecture `{0}` is not accepted")]
+    UnsupportedArch(String),
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -214,6 +767,18 @@ impl FromStr for SupportedArch {
 pub struct GitLabRelease {
     pub tag_name: String,
     pub assets: Assets,
+    #[serde(default, rename = "released_at")]
+    pub released_at: Option<String>,
+}
+
+/// The parts of a GitLab project shown in provider metadata.
+#[derive(Debug, Clone, Deserialize)]
+pub struct GitLabProject {
+    #[serde(default)]
+    pub description: Option<String>,
+    pub web_url: String,
+    #[serde(default)]
+    pub topics: Vec<String>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -249,6 +814,7 @@ mod tests {
             assets: Assets {
                 links: link_names.into_iter().map(make_link).collect(),
             },
+            released_at: None,
         }
     }
 
@@ -259,8 +825,8 @@ mod tests {
             vec![
                 "terraform-provider-example_SHA256SUMS",
                 "terraform-provider-example_SHA256SUMS.sig",
-                "terraform-provider-example_linux_amd64.zip",
-                "terraform-provider-example_darwin_arm64.zip",
+                "terraform-provider-example_1.2.3_linux_amd64.zip",
+                "terraform-provider-example_1.2.3_darwin_arm64.zip",
             ],
         );
 
@@ -292,7 +858,7 @@ mod tests {
             vec![
                 "provider_SHA256SUMS",
                 "provider_SHA256SUMS.sig",
-                "provider_linux_386.zip",
+                "terraform-provider-example_2.0.0-beta.1_linux_386.zip",
             ],
         );
 
@@ -345,7 +911,10 @@ mod tests {
     fn try_from_missing_shasums_sig() {
         let release = make_release(
             "v1.0.0",
-            vec!["provider_SHA256SUMS", "provider_linux_amd64.zip"],
+            vec![
+                "provider_SHA256SUMS",
+                "terraform-provider-example_1.0.0_linux_amd64.zip",
+            ],
         );
 
         let result = VersionInfo::try_from(&release);
@@ -361,7 +930,10 @@ mod tests {
     fn try_from_missing_shasums() {
         let release = make_release(
             "v1.0.0",
-            vec!["provider_SHA256SUMS.sig", "provider_linux_amd64.zip"],
+            vec![
+                "provider_SHA256SUMS.sig",
+                "terraform-provider-example_1.0.0_linux_amd64.zip",
+            ],
         );
 
         let result = VersionInfo::try_from(&release);
@@ -395,9 +967,9 @@ mod tests {
             vec![
                 "provider_SHA256SUMS",
                 "provider_SHA256SUMS.sig",
-                "provider_linux_amd64.zip",
-                "provider_unsupportedos_amd64.zip",
-                "provider_linux_unsupportedarch.zip",
+                "terraform-provider-example_1.0.0_linux_amd64.zip",
+                "terraform-provider-example_1.0.0_unsupportedos_amd64.zip",
+                "terraform-provider-example_1.0.0_linux_unsupportedarch.zip",
                 "invalid_format.zip",
             ],
         );
@@ -418,16 +990,16 @@ mod tests {
             vec![
                 "provider_SHA256SUMS",
                 "provider_SHA256SUMS.sig",
-                "provider_linux_amd64.zip",
-                "provider_linux_arm64.zip",
-                "provider_linux_arm.zip",
-                "provider_linux_386.zip",
-                "provider_darwin_amd64.zip",
-                "provider_darwin_arm64.zip",
-                "provider_windows_amd64.zip",
-                "provider_freebsd_amd64.zip",
-                "provider_openbsd_amd64.zip",
-                "provider_solaris_amd64.zip",
+                "terraform-provider-example_1.0.0_linux_amd64.zip",
+                "terraform-provider-example_1.0.0_linux_arm64.zip",
+                "terraform-provider-example_1.0.0_linux_arm.zip",
+                "terraform-provider-example_1.0.0_linux_386.zip",
+                "terraform-provider-example_1.0.0_darwin_amd64.zip",
+                "terraform-provider-example_1.0.0_darwin_arm64.zip",
+                "terraform-provider-example_1.0.0_windows_amd64.zip",
+                "terraform-provider-example_1.0.0_freebsd_amd64.zip",
+                "terraform-provider-example_1.0.0_openbsd_amd64.zip",
+                "terraform-provider-example_1.0.0_solaris_amd64.zip",
             ],
         );
 
@@ -445,8 +1017,8 @@ mod tests {
             vec![
                 "provider_SHA256SUMS",
                 "provider_SHA256SUMS.sig",
-                "provider_linux_amd64.zip",
-                "provider_linux_arm64.tar.gz",
+                "terraform-provider-example_1.0.0_linux_amd64.zip",
+                "terraform-provider-example_1.0.0_linux_arm64.tar.gz",
                 "README.md",
             ],
         );
@@ -459,4 +1031,381 @@ mod tests {
         assert_eq!(version_info.platforms[0].os, "linux");
         assert_eq!(version_info.platforms[0].arch, "amd64");
     }
+
+    #[test]
+    fn try_from_monorepo_tags_with_pattern() {
+        let tags = TagPattern::new("^provider-foo/v(.+)$").unwrap();
+        let naming = ReleaseNaming {
+            provider_type: Some("foo"),
+            tags: &tags,
+            ..ReleaseNaming::default()
+        };
+        let foo = make_release(
+            "provider-foo/v1.2.3",
+            vec![
+                "provider_SHA256SUMS",
+                "provider_SHA256SUMS.sig",
+                "terraform-provider-foo_1.2.3_linux_amd64.zip",
+            ],
+        );
+        let bar = make_release(
+            "provider-bar/v0.4.0",
+            vec!["provider_SHA256SUMS", "provider_SHA256SUMS.sig"],
+        );
+
+        let version_info = VersionInfo::try_from((&foo, &naming)).unwrap();
+
+        assert_eq!(version_info.version, "1.2.3");
+        assert_eq!(version_info.platforms.len(), 1);
+        match VersionInfo::try_from((&bar, &naming)).unwrap_err() {
+            TryFromGitLabError::InvalidVersion(tag, msg) => {
+                assert_eq!(tag, "provider-bar/v0.4.0");
+                assert!(msg.contains("^provider-foo/v(.+)$"));
+            }
+            _ => panic!("expected InvalidVersion error"),
+        }
+    }
+
+    #[test]
+    fn tag_pattern_needs_one_capture_group() {
+        assert!(TagPattern::new("^provider-foo/v.+$").is_err());
+        assert!(TagPattern::new("^(provider-foo)/v(.+)$").is_err());
+        assert!(TagPattern::new("^provider-foo/v(").is_err());
+    }
+
+    fn platforms(
+        template: &str,
+        provider_type: Option<&str>,
+        tag: &str,
+        asset: &str,
+    ) -> Vec<Platform> {
+        let assets = AssetTemplate::new(template).unwrap();
+        let naming = ReleaseNaming {
+            provider_type,
+            assets: &assets,
+            ..ReleaseNaming::default()
+        };
+        let release = make_release(
+            tag,
+            vec!["provider_SHA256SUMS", "provider_SHA256SUMS.sig", asset],
+        );
+
+        VersionInfo::try_from((&release, &naming))
+            .unwrap()
+            .platforms
+    }
+
+    #[test]
+    fn asset_template_matches_published_naming_variants() {
+        let default = AssetTemplate::DEFAULT;
+        let cases = [
+            // goreleaser output from the HashiCorp scaffolding
+            (
+                default,
+                None,
+                "v1.2.3",
+                "terraform-provider-foo_1.2.3_linux_amd64.zip",
+            ),
+            // provider names containing the separator
+            (
+                default,
+                None,
+                "v1.2.3",
+                "terraform-provider-my_thing_1.2.3_darwin_arm64.zip",
+            ),
+            (
+                default,
+                Some("my_thing"),
+                "v1.2.3",
+                "terraform-provider-my_thing_1.2.3_windows_386.zip",
+            ),
+            // prerelease and build metadata
+            (
+                default,
+                None,
+                "v2.0.0-rc.1",
+                "terraform-provider-foo_2.0.0-rc.1_linux_arm.zip",
+            ),
+            (
+                default,
+                None,
+                "v1.0.0+build.5",
+                "terraform-provider-foo_1.0.0+build.5_freebsd_amd64.zip",
+            ),
+            // versions that keep their `v` in the filename
+            (
+                "terraform-provider-{type}_v{version}_{os}_{arch}.zip",
+                Some("foo"),
+                "v1.2.3",
+                "terraform-provider-foo_v1.2.3_linux_amd64.zip",
+            ),
+            // dash-separated names without the `terraform-provider-` prefix
+            (
+                "{type}-{version}-{os}-{arch}.zip",
+                Some("foo"),
+                "v1.2.3",
+                "foo-1.2.3-linux-arm64.zip",
+            ),
+        ];
+
+        for (template, provider_type, tag, asset) in cases {
+            assert_eq!(
+                platforms(template, provider_type, tag, asset).len(),
+                1,
+                "{template} should match {asset}"
+            );
+        }
+    }
+
+    #[test]
+    fn asset_template_rejects_other_providers_and_versions() {
+        let default = AssetTemplate::DEFAULT;
+
+        assert!(
+            platforms(
+                default,
+                Some("foo"),
+                "v1.2.3",
+                "terraform-provider-bar_1.2.3_linux_amd64.zip"
+            )
+            .is_empty()
+        );
+        assert!(
+            platforms(
+                default,
+                None,
+                "v1.2.3",
+                "terraform-provider-foo_1.2.4_linux_amd64.zip"
+            )
+            .is_empty()
+        );
+        assert!(
+            platforms(
+                default,
+                None,
+                "v1.2.3",
+                "terraform-provider-foo_linux_amd64.zip"
+            )
+            .is_empty()
+        );
+    }
+
+    #[test]
+    fn asset_template_needs_os_and_arch_once() {
+        assert!(AssetTemplate::new("{type}_{version}_{os}.zip").is_err());
+        assert!(AssetTemplate::new("{os}_{arch}_{os}.zip").is_err());
+        assert!(AssetTemplate::new("{type}_{release}_{os}_{arch}.zip").is_err());
+        assert!(AssetTemplate::new("{type}_{version}_{os}_{arch.zip").is_err());
+    }
+
+    #[test]
+    fn default_platforms_include_less_common_builds() {
+        let release = make_release(
+            "v1.0.0",
+            vec![
+                "provider_SHA256SUMS",
+                "provider_SHA256SUMS.sig",
+                "terraform-provider-example_1.0.0_netbsd_amd64.zip",
+                "terraform-provider-example_1.0.0_linux_ppc64le.zip",
+                "terraform-provider-example_1.0.0_linux_s390x.zip",
+                "terraform-provider-example_1.0.0_linux_riscv64.zip",
+                "terraform-provider-example_1.0.0_linux_loong64.zip",
+            ],
+        );
+
+        let version_info = VersionInfo::try_from(&release).unwrap();
+
+        assert_eq!(version_info.platforms.len(), 5);
+    }
+
+    #[test]
+    fn reports_assets_outside_configured_platforms() {
+        let platforms = PlatformsConfig {
+            os: vec!["linux".to_string()],
+            arch: vec!["amd64".to_string()],
+        };
+        let naming = ReleaseNaming {
+            platforms: &platforms,
+            ..ReleaseNaming::default()
+        };
+        let release = make_release(
+            "v1.0.0",
+            vec![
+                "provider_SHA256SUMS",
+                "provider_SHA256SUMS.sig",
+                "terraform-provider-example_1.0.0_linux_amd64.zip",
+                "terraform-provider-example_1.0.0_darwin_amd64.zip",
+                "terraform-provider-example_1.0.0_linux_arm64.zip",
+                "terraform-provider-example_linux_amd64.zip",
+            ],
+        );
+
+        let parsed = ParsedRelease::try_from((&release, &naming)).unwrap();
+
+        assert_eq!(parsed.version.platforms.len(), 1);
+        let rejected: Vec<_> = parsed
+            .rejected
+            .iter()
+            .map(|rejected| format!("{}: {}", rejected.name, rejected.reason))
+            .collect();
+        assert_eq!(
+            rejected,
+            vec![
+                "terraform-provider-example_1.0.0_darwin_amd64.zip: operating system `darwin` is not accepted",
+                "terraform-provider-example_1.0.0_linux_arm64.zip: architecture `arm64` is not accepted",
+                "terraform-provider-example_linux_amd64.zip: name does not match the asset template",
+            ]
+        );
+    }
+
+    #[test]
+    fn diagnoses_skipped_releases_and_assets() {
+        let platforms = PlatformsConfig {
+            os: vec!["linux".to_string()],
+            arch: vec!["amd64".to_string()],
+        };
+        let naming = ReleaseNaming {
+            provider_type: Some("example"),
+            platforms: &platforms,
+            ..ReleaseNaming::default()
+        };
+        let accepted = make_release(
+            "v1.0.0",
+            vec![
+                "provider_SHA256SUMS",
+                "provider_SHA256SUMS.sig",
+                "terraform-provider-example_1.0.0_linux_amd64.zip",
+                "terraform-provider-example_1.0.0_darwin_amd64.zip",
+                "CHANGELOG.md",
+            ],
+        );
+        let unsigned = make_release(
+            "v1.1.0",
+            vec![
+                "provider_SHA256SUMS",
+                "terraform-provider-example_1.1.0_linux_amd64.zip",
+            ],
+        );
+
+        let accepted = diagnose_release(&accepted, &naming);
+        let unsigned = diagnose_release(&unsigned, &naming);
+
+        assert!(accepted.accepted);
+        assert_eq!(accepted.version.as_deref(), Some("1.0.0"));
+        let reasons: Vec<_> = accepted
+            .assets
+            .iter()
+            .map(|asset| (asset.name.as_str(), asset.reason.as_deref()))
+            .collect();
+        assert_eq!(
+            reasons,
+            vec![
+                ("provider_SHA256SUMS", None),
+                ("provider_SHA256SUMS.sig", None),
+                ("terraform-provider-example_1.0.0_linux_amd64.zip", None),
+                (
+                    "terraform-provider-example_1.0.0_darwin_amd64.zip",
+                    Some("operating system `darwin` is not accepted")
+                ),
+                (
+                    "CHANGELOG.md",
+                    Some("not a package, checksum or signature file")
+                ),
+            ]
+        );
+        assert!(!unsigned.accepted);
+        assert_eq!(unsigned.version, None);
+        assert_eq!(
+            unsigned.reason.as_deref(),
+            Some("no checksum signature asset (`*SUMS.sig`)")
+        );
+        assert!(unsigned.assets.iter().all(|asset| !asset.accepted));
+    }
+
+    #[test]
+    fn publish_dates_come_from_matching_releases() {
+        let naming = ReleaseNaming {
+            provider_type: Some("example"),
+            ..ReleaseNaming::default()
+        };
+        let assets = |version: &str| {
+            vec![
+                "provider_SHA256SUMS".to_string(),
+                "provider_SHA256SUMS.sig".to_string(),
+                format!("terraform-provider-example_{version}_linux_amd64.zip"),
+            ]
+        };
+        let release = |tag: &str, version: &str, released_at: Option<&str>| GitLabRelease {
+            released_at: released_at.map(str::to_string),
+            ..make_release(tag, assets(version).iter().map(String::as_str).collect())
+        };
+
+        let published = published_versions(
+            &[
+                release("v1.0.0", "1.0.0", Some("2024-01-02T03:04:05Z")),
+                release("v1.1.0", "1.1.0", None),
+                release("nightly", "1.2.0", Some("2024-03-01T00:00:00Z")),
+            ],
+            &naming,
+        );
+
+        assert_eq!(
+            published,
+            BTreeMap::from([("1.0.0".to_string(), "2024-01-02T03:04:05Z".to_string())])
+        );
+    }
+
+    #[test]
+    fn packages_are_found_by_platform_with_their_checksum() {
+        let release = make_release(
+            "v1.2.3",
+            vec![
+                "terraform-provider-example_1.2.3_SHA256SUMS",
+                "terraform-provider-example_1.2.3_SHA256SUMS.sig",
+                "terraform-provider-example_1.2.3_linux_amd64.zip",
+                "terraform-provider-example_1.2.3_darwin_arm64.zip",
+            ],
+        );
+        let parsed = ParsedRelease::try_from((&release, &ReleaseNaming::default())).unwrap();
+        let sums = "\
+aaa111  terraform-provider-example_1.2.3_linux_amd64.zip
+bbb222 *terraform-provider-example_1.2.3_darwin_arm64.zip
+";
+
+        let package = parsed.package("darwin", "arm64").unwrap();
+
+        assert_eq!(
+            package.direct_asset_url,
+            "https://example.com/direct/terraform-provider-example_1.2.3_darwin_arm64.zip"
+        );
+        assert_eq!(
+            parsed.shasums.name,
+            "terraform-provider-example_1.2.3_SHA256SUMS"
+        );
+        assert!(parsed.package("windows", "amd64").is_none());
+        assert_eq!(shasum_for(sums, &package.name).as_deref(), Some("bbb222"));
+        assert_eq!(
+            shasum_for(sums, "terraform-provider-example_1.2.3_linux_amd64.zip").as_deref(),
+            Some("aaa111")
+        );
+        assert_eq!(shasum_for(sums, "other.zip"), None);
+    }
+
+    #[test]
+    fn project_paths_are_encoded_once() {
+        use gitlab::api::Endpoint;
+
+        assert_eq!(
+            project_endpoint("group/sub group/project")
+                .unwrap()
+                .endpoint(),
+            "projects/group%2Fsub%20group%2Fproject"
+        );
+        assert_eq!(
+            releases_endpoint("group/sub group/project")
+                .unwrap()
+                .endpoint(),
+            "projects/group%2Fsub%20group%2Fproject/releases"
+        );
+    }
 }
@@ -0,0 +1,233 @@
+use std::sync::Arc;
+
+use tracing::warn;
+
+use crate::index::{IndexError, MetadataIndex, ProviderRef, sync_provider};
+use crate::params::{Arch, Namespace, Os, ProviderType, Version};
+use crate::types::{Package, ReleaseDiagnostic, VersionInfo};
+
+use super::{Backend, Freshness, ProviderBackendError, ProviderDetails, Result};
+
+/// Serves reads from the persistent metadata index, falling back to the wrapped backend for
+/// providers and packages the sync job has not recorded yet.
+pub struct IndexedBackend {
+    inner: Arc<dyn Backend>,
+    index: Arc<MetadataIndex>,
+}
+
+impl IndexedBackend {
+    pub fn new(inner: Arc<dyn Backend>, index: Arc<MetadataIndex>) -> Self {
+        Self { inner, index }
+    }
+}
+
+impl Backend for IndexedBackend {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn health(&self) -> Result<()> {
+        self.inner.health()
+    }
+
+    fn list_provider_versions(
+        &self,
+        namespace: Namespace,
+        provider_type: ProviderType,
+    ) -> Result<Vec<VersionInfo>> {
+        self.list_provider_versions_with_freshness(namespace, provider_type)
+            .map(|(versions, _)| versions)
+    }
+
+    fn list_provider_versions_with_freshness(
+        &self,
+        namespace: Namespace,
+        provider_type: ProviderType,
+    ) -> Result<(Vec<VersionInfo>, Option<Freshness>)> {
+        match self.index.versions(&namespace, &provider_type) {
+            Ok(Some(versions)) => return Ok((versions, None)),
+            Ok(None) => {}
+            Err(error) => warn!("Index lookup failed, falling back to backend: {error}"),
+        }
+
+        self.inner
+            .list_provider_versions_with_freshness(namespace, provider_type)
+    }
+
+    fn find_provider_package(
+        &self,
+        namespace: Namespace,
+        provider_type: ProviderType,
+        version: Version,
+        os: Os,
+        arch: Arch,
+    ) -> Result<Package> {
+        match self
+            .index
+            .package(&namespace, &provider_type, &version, &os, &arch)
+        {
+            Ok(Some(package)) => return Ok(package),
+            Ok(None) => {}
+            Err(error) => warn!("Index lookup failed, falling back to backend: {error}"),
+        }
+
+        self.inner
+            .find_provider_package(namespace, provider_type, version, os, arch)
+    }
+
+    fn diagnose_provider(
+        &self,
+        namespace: Namespace,
+        provider_type: ProviderType,
+    ) -> Result<Vec<ReleaseDiagnostic>> {
+        self.inner.diagnose_provider(namespace, provider_type)
+    }
+
+    /// Adds the providers recorded in the index to those the wrapped backend lists.
+    fn list_providers(&self) -> Result<Vec<ProviderRef>> {
+        let mut providers = self.inner.list_providers()?;
+        match self.index.providers() {
+            Ok(indexed) => {
+                for provider in indexed {
+                    let (Ok(namespace), Ok(provider_type)) = (
+                        Namespace::new(provider.namespace),
+                        ProviderType::new(provider.provider_type),
+                    ) else {
+                        continue;
+                    };
+                    let provider = ProviderRef {
+                        namespace,
+                        provider_type,
+                    };
+                    if !providers.contains(&provider) {
+                        providers.push(provider);
+                    }
+                }
+            }
+            Err(error) => warn!("Index lookup failed, listing backend providers only: {error}"),
+        }
+        Ok(providers)
+    }
+
+    fn describe_provider(
+        &self,
+        namespace: Namespace,
+        provider_type: ProviderType,
+    ) -> Result<ProviderDetails> {
+        self.inner.describe_provider(namespace, provider_type)
+    }
+
+    /// Re-syncs an indexed provider, since its index row would otherwise keep answering for it
+    /// until the next scheduled sync. Providers not in the index are already read through.
+    fn refresh_provider(&self, namespace: Namespace, provider_type: ProviderType) -> Result<()> {
+        self.inner
+            .refresh_provider(namespace.clone(), provider_type.clone())?;
+        let indexed = self
+            .index
+            .versions(&namespace, &provider_type)
+            .map_err(ProviderBackendError::storage)?;
+        if indexed.is_none() {
+            return Ok(());
+        }
+
+        let provider = ProviderRef {
+            namespace,
+            provider_type,
+        };
+        // Without package checks: anything changed through this registry was checked on upload.
+        match sync_provider(self.inner.as_ref(), &self.index, &provider, None) {
+            Ok(()) => Ok(()),
+            Err(IndexError::Backend(error)) => Err(error),
+            Err(IndexError::Database(error)) => Err(ProviderBackendError::storage(error)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::{ProviderRef, sync_provider};
+    use crate::providers::{FakeBackend, ProviderBackendError};
+
+    /// Fails every call, proving reads were answered by the index.
+    struct UnavailableBackend;
+
+    impl Backend for UnavailableBackend {
+        fn name(&self) -> &'static str {
+            "unavailable"
+        }
+
+        fn health(&self) -> Result<()> {
+            Err(ProviderBackendError::storage("unavailable"))
+        }
+
+        fn list_provider_versions(
+            &self,
+            _: Namespace,
+            _: ProviderType,
+        ) -> Result<Vec<VersionInfo>> {
+            Err(ProviderBackendError::storage("unavailable"))
+        }
+
+        fn find_provider_package(
+            &self,
+            _: Namespace,
+            _: ProviderType,
+            _: Version,
+            _: Os,
+            _: Arch,
+        ) -> Result<Package> {
+            Err(ProviderBackendError::storage("unavailable"))
+        }
+    }
+
+    fn synced_index() -> Arc<MetadataIndex> {
+        let index = Arc::new(MetadataIndex::in_memory().unwrap());
+        sync_provider(
+            &FakeBackend,
+            &index,
+            &ProviderRef {
+                namespace: "hashicorp".parse().unwrap(),
+                provider_type: "aws".parse().unwrap(),
+            },
+            None,
+        )
+        .unwrap();
+        index
+    }
+
+    #[test]
+    fn reads_indexed_providers_without_backend() {
+        let backend = IndexedBackend::new(Arc::new(UnavailableBackend), synced_index());
+
+        let versions = backend
+            .list_provider_versions("hashicorp".parse().unwrap(), "aws".parse().unwrap())
+            .unwrap();
+        let package = backend
+            .find_provider_package(
+                "hashicorp".parse().unwrap(),
+                "aws".parse().unwrap(),
+                "0.9.0".parse().unwrap(),
+                "linux".parse().unwrap(),
+                "amd64".parse().unwrap(),
+            )
+            .unwrap();
+
+        assert_eq!(versions.len(), 2);
+        assert_eq!(
+            package.filename,
+            "terraform-provider-aws_0.9.0_linux_amd64.zip"
+        );
+    }
+
+    #[test]
+    fn falls_back_to_backend_for_unindexed_providers() {
+        let backend = IndexedBackend::new(Arc::new(FakeBackend), synced_index());
+
+        let versions = backend
+            .list_provider_versions("acme".parse().unwrap(), "foo".parse().unwrap())
+            .unwrap();
+
+        assert_eq!(versions.len(), 2);
+    }
+}
@@ -0,0 +1,113 @@
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::index::ProviderRef;
+use crate::metrics::Metrics;
+use crate::params::{Arch, Namespace, Os, ProviderType, Version};
+use crate::types::{Package, ReleaseDiagnostic, VersionInfo};
+
+use super::{Backend, ProviderDetails, Result};
+
+/// Wraps another backend, recording call counts, errors and latency for every method and
+/// running each call inside a `backend_call` span.
+pub struct InstrumentedBackend {
+    inner: Arc<dyn Backend>,
+    metrics: Metrics,
+}
+
+impl InstrumentedBackend {
+    pub fn new(inner: Arc<dyn Backend>, metrics: Metrics) -> Self {
+        Self { inner, metrics }
+    }
+
+    fn observe<T>(&self, method: &str, call: impl FnOnce() -> Result<T>) -> Result<T> {
+        let span = tracing::info_span!("backend_call", backend = self.inner.name(), method);
+        let started = Instant::now();
+        let result = span.in_scope(call);
+        self.metrics
+            .observe_backend_call(self.inner.name(), method, &result, started.elapsed());
+        result
+    }
+}
+
+impl Backend for InstrumentedBackend {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn health(&self) -> Result<()> {
+        self.observe("health", || self.inner.health())
+    }
+
+    fn list_provider_versions(
+        &self,
+        namespace: Namespace,
+        provider_type: ProviderType,
+    ) -> Result<Vec<VersionInfo>> {
+        self.observe("list_provider_versions", || {
+            self.inner.list_provider_versions(namespace, provider_type)
+        })
+    }
+
+    fn find_provider_package(
+        &self,
+        namespace: Namespace,
+        provider_type: ProviderType,
+        version: Version,
+        os: Os,
+        arch: Arch,
+    ) -> Result<Package> {
+        self.observe("find_provider_package", || {
+            self.inner
+                .find_provider_package(namespace, provider_type, version, os, arch)
+        })
+    }
+
+    fn diagnose_provider(
+        &self,
+        namespace: Namespace,
+        provider_type: ProviderType,
+    ) -> Result<Vec<ReleaseDiagnostic>> {
+        self.observe("diagnose_provider", || {
+            self.inner.diagnose_provider(namespace, provider_type)
+        })
+    }
+
+    fn list_providers(&self) -> Result<Vec<ProviderRef>> {
+        self.observe("list_providers", || self.inner.list_providers())
+    }
+
+    fn describe_provider(
+        &self,
+        namespace: Namespace,
+        provider_type: ProviderType,
+    ) -> Result<ProviderDetails> {
+        self.observe("describe_provider", || {
+            self.inner.describe_provider(namespace, provider_type)
+        })
+    }
+
+    fn refresh_provider(&self, namespace: Namespace, provider_type: ProviderType) -> Result<()> {
+        self.inner.refresh_provider(namespace, provider_type)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::FakeBackend;
+
+    #[test]
+    fn records_backend_calls() {
+        let metrics = Metrics::new().unwrap();
+        let backend = InstrumentedBackend::new(Arc::new(FakeBackend), metrics.clone());
+
+        backend
+            .list_provider_versions("hashicorp".parse().unwrap(), "aws".parse().unwrap())
+            .unwrap();
+
+        assert!(metrics.render().unwrap().contains(
+            r#"terraform_registry_backend_calls_total{backend="fake",method="list_provider_versions",result="ok"} 1"#
+        ));
+    }
+}
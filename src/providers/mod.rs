@@ -1,55 +1,268 @@
+mod cached;
+mod coalesced;
 mod fake;
+mod filesystem;
 mod gitlabrelease;
+mod indexed;
+mod instrumented;
+mod overlay;
+mod s3;
+mod store;
 
+pub use cached::CachingBackend;
+pub use coalesced::CoalescingBackend;
 pub use fake::FakeBackend;
-pub use gitlabrelease::GitLabBackend;
+pub use filesystem::{FilesystemStore, PARTIAL_SUFFIX};
+pub use gitlabrelease::{AssetTemplate, GitLabBackend, TagPattern};
+pub use indexed::IndexedBackend;
+pub use instrumented::InstrumentedBackend;
+pub use overlay::OverlayBackend;
+pub use s3::S3Store;
+pub use store::{ObjectStore, StoreBackend};
 
-use crate::types::{Package, VersionInfo};
+use crate::index::ProviderRef;
+use crate::params::{Arch, Namespace, Os, ProviderType, Version};
+use crate::publish::ProviderRelease;
+use crate::types::{Package, ReleaseDiagnostic, VersionInfo};
+use axum::Json;
+use axum::http::{HeaderValue, StatusCode, header};
 use axum::response::{IntoResponse, Response};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::warn;
 
 pub trait Backend: Send + Sync {
+    /// Short identifier used when reporting on this backend, e.g. in `/ready`.
+    fn name(&self) -> &'static str;
+
+    /// Checks that the backend can currently serve requests.
+    fn health(&self) -> Result<()>;
+
     fn list_provider_versions(
         &self,
-        namespace: String,
-        provider_type: String,
+        namespace: Namespace,
+        provider_type: ProviderType,
     ) -> Result<Vec<VersionInfo>>;
 
+    /// Like `list_provider_versions`, but also reports how old the answer is when it was served
+    /// from a cache. Only caching backends need to override this.
+    fn list_provider_versions_with_freshness(
+        &self,
+        namespace: Namespace,
+        provider_type: ProviderType,
+    ) -> Result<(Vec<VersionInfo>, Option<Freshness>)> {
+        self.list_provider_versions(namespace, provider_type)
+            .map(|versions| (versions, None))
+    }
+
     fn find_provider_package(
         &self,
-        namespace: String,
-        provider_type: String,
-        version: String,
-        os: String,
-        arch: String,
+        namespace: Namespace,
+        provider_type: ProviderType,
+        version: Version,
+        os: Os,
+        arch: Arch,
     ) -> Result<Package>;
+
+    /// Lists every release the backend holds for a provider, including the ones it skips, with
+    /// the reason each release and asset was accepted or rejected. Backends that serve exactly
+    /// what was stored have nothing to explain and report `NotFound`.
+    fn diagnose_provider(
+        &self,
+        _namespace: Namespace,
+        _provider_type: ProviderType,
+    ) -> Result<Vec<ReleaseDiagnostic>> {
+        Err(ProviderBackendError::NotFound)
+    }
+
+    /// Providers the backend can enumerate, for discovery. Backends that answer for whatever
+    /// name they are asked about list none.
+    fn list_providers(&self) -> Result<Vec<ProviderRef>> {
+        Ok(Vec::new())
+    }
+
+    /// Descriptive details about a provider, beyond what the registry protocol serves.
+    fn describe_provider(
+        &self,
+        _namespace: Namespace,
+        _provider_type: ProviderType,
+    ) -> Result<ProviderDetails> {
+        Ok(ProviderDetails::default())
+    }
+
+    /// Forgets anything remembered about a provider, after it changed through this registry, so
+    /// the next read sees the change.
+    fn refresh_provider(&self, _namespace: Namespace, _provider_type: ProviderType) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// A backend that new provider versions can be published to.
+pub trait WritableBackend: Backend {
+    /// Stores a validated release. Fails with `AlreadyExists` if the version was published
+    /// before; published versions are immutable.
+    fn publish_provider_version(&self, release: &ProviderRelease) -> Result<()>;
 }
 
 pub type Result<T> = std::result::Result<T, ProviderBackendError>;
 
+/// What a backend knows about a provider besides its versions. Every part is optional.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProviderDetails {
+    pub description: Option<String>,
+    /// Where the provider's source code lives.
+    pub source: Option<String>,
+    /// Free-form keywords, such as GitLab project topics.
+    pub tags: Vec<String>,
+    /// When each version was published, as RFC 3339, keyed by version.
+    pub published: BTreeMap<String, String>,
+}
+
+/// How old a cached answer is and whether it was still within its TTL.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Freshness {
+    pub age: Duration,
+    pub staleness: Staleness,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Staleness {
+    Fresh,
+    /// Past its TTL; a background refresh has been started.
+    Revalidating,
+    /// Past its TTL and the upstream failed to provide a replacement.
+    RevalidationFailed,
+}
+
 use thiserror::Error;
 
-#[allow(dead_code)]
-#[derive(Error, Debug)]
+/// The error behind a backend failure. It is logged, but never shown to clients.
+pub type Cause = Arc<dyn std::error::Error + Send + Sync>;
+
+#[derive(Error, Debug, Clone)]
 pub enum ProviderBackendError {
     #[error("not found")]
     NotFound,
     #[error("storage error")]
-    StorageError,
+    StorageError(#[source] Cause),
+    #[error("already exists")]
+    AlreadyExists,
+    /// The upstream refused the registry's credentials.
+    #[error("the registry is not authorized to read this provider")]
+    Unauthorized(#[source] Cause),
+    #[error("the registry's upstream is rate limiting requests, try again later")]
+    RateLimited {
+        retry_after: Option<Duration>,
+        #[source]
+        cause: Cause,
+    },
+    #[error("this provider version is not available for {os}_{arch}")]
+    PlatformUnsupported { os: String, arch: String },
+    #[error("the registry's upstream did not answer in time")]
+    UpstreamTimeout(#[source] Cause),
 }
 
+/// An unsuccessful HTTP answer from an upstream, kept as the cause of the error it maps to.
+#[derive(Error, Debug)]
+#[error("{upstream} answered {status}")]
+pub struct UpstreamStatus {
+    pub upstream: &'static str,
+    pub status: u16,
+}
+
+impl ProviderBackendError {
+    /// A storage failure, keeping what went wrong as its cause.
+    pub fn storage(cause: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        Self::StorageError(Arc::from(cause.into()))
+    }
+
+    /// Maps an unsuccessful status from an upstream to the error it stands for.
+    pub fn from_status(upstream: &'static str, status: u16, retry_after: Option<Duration>) -> Self {
+        let cause: Cause = Arc::new(UpstreamStatus { upstream, status });
+        match status {
+            401 | 403 => Self::Unauthorized(cause),
+            404 => Self::NotFound,
+            408 | 504 => Self::UpstreamTimeout(cause),
+            429 => Self::RateLimited { retry_after, cause },
+            _ => Self::StorageError(cause),
+        }
+    }
+
+    /// Maps a failed request to an upstream, separating timeouts from other failures.
+    pub fn from_request(error: reqwest::Error) -> Self {
+        if error.is_timeout() {
+            return Self::UpstreamTimeout(Arc::new(error));
+        }
+        match error.status() {
+            Some(status) => Self::from_status("upstream", status.as_u16(), None),
+            None => Self::StorageError(Arc::new(error)),
+        }
+    }
+
+    /// Short label for metrics.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::NotFound => "not_found",
+            Self::StorageError(_) => "storage_error",
+            Self::AlreadyExists => "already_exists",
+            Self::Unauthorized(_) => "unauthorized",
+            Self::RateLimited { .. } => "rate_limited",
+            Self::PlatformUnsupported { .. } => "platform_unsupported",
+            Self::UpstreamTimeout(_) => "upstream_timeout",
+        }
+    }
+
+    /// Whether the upstream could not answer right now, as opposed to answering with an error.
+    pub fn is_unavailable(&self) -> bool {
+        matches!(
+            self,
+            Self::StorageError(_) | Self::RateLimited { .. } | Self::UpstreamTimeout(_)
+        )
+    }
+
+    fn status(&self) -> StatusCode {
+        match self {
+            Self::NotFound | Self::PlatformUnsupported { .. } => StatusCode::NOT_FOUND,
+            Self::StorageError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::AlreadyExists => StatusCode::CONFLICT,
+            Self::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            Self::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+            Self::UpstreamTimeout(_) => StatusCode::GATEWAY_TIMEOUT,
+        }
+    }
+}
+
+/// Answers with the `{"errors": [...]}` body the Terraform CLI shows to users. The cause, if
+/// any, only goes to the log.
 impl IntoResponse for ProviderBackendError {
     fn into_response(self) -> Response {
-        match self {
-            Self::NotFound => axum::http::StatusCode::NOT_FOUND.into_response(),
-            Self::StorageError => axum::http::StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+        if let Some(cause) = std::error::Error::source(&self) {
+            warn!(error = %self, %cause, "Backend request failed");
+        }
+
+        let mut response = (
+            self.status(),
+            Json(serde_json::json!({ "errors": [self.to_string()] })),
+        )
+            .into_response();
+        if let Self::RateLimited {
+            retry_after: Some(retry_after),
+            ..
+        } = self
+        {
+            response.headers_mut().insert(
+                header::RETRY_AFTER,
+                HeaderValue::from(retry_after.as_secs().max(1)),
+            );
         }
+        response
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::providers::ProviderBackendError;
-    use axum::response::IntoResponse;
+    use super::*;
 
     #[test]
     fn into_response_for_error() {
@@ -59,7 +272,69 @@ mod tests {
         );
         assert_eq!(
             axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-            ProviderBackendError::StorageError.into_response().status()
+            ProviderBackendError::storage("disk full")
+                .into_response()
+                .status()
+        );
+        assert_eq!(
+            axum::http::StatusCode::CONFLICT,
+            ProviderBackendError::AlreadyExists.into_response().status()
+        );
+        assert_eq!(
+            axum::http::StatusCode::GATEWAY_TIMEOUT,
+            ProviderBackendError::UpstreamTimeout(Arc::new(std::io::Error::from(
+                std::io::ErrorKind::TimedOut
+            )))
+            .into_response()
+            .status()
+        );
+    }
+
+    #[tokio::test]
+    async fn errors_carry_terraform_messages() {
+        let response = ProviderBackendError::PlatformUnsupported {
+            os: "plan9".to_string(),
+            arch: "amd64".to_string(),
+        }
+        .into_response();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap(),
+            serde_json::json!({
+                "errors": ["this provider version is not available for plan9_amd64"]
+            })
+        );
+    }
+
+    #[test]
+    fn upstream_statuses_map_to_errors() {
+        let rate_limited =
+            ProviderBackendError::from_status("gitlab", 429, Some(Duration::from_secs(30)));
+
+        assert!(matches!(
+            ProviderBackendError::from_status("s3", 403, None),
+            ProviderBackendError::Unauthorized(_)
+        ));
+        assert!(matches!(
+            ProviderBackendError::from_status("s3", 504, None),
+            ProviderBackendError::UpstreamTimeout(_)
+        ));
+        assert!(matches!(
+            ProviderBackendError::from_status("s3", 404, None),
+            ProviderBackendError::NotFound
+        ));
+        assert_eq!(
+            std::error::Error::source(&rate_limited)
+                .unwrap()
+                .to_string(),
+            "gitlab answered 429"
         );
+        let response = rate_limited.into_response();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "30");
     }
 }
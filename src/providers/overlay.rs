@@ -0,0 +1,153 @@
+use std::sync::Arc;
+
+use crate::index::ProviderRef;
+use crate::overlay::{VersionOverlay, VersionStatus};
+use crate::params::{Arch, Namespace, Os, ProviderType, Version};
+use crate::types::{Package, ReleaseDiagnostic, VersionInfo};
+
+use super::{Backend, Freshness, ProviderDetails, Result};
+
+/// Applies yanked and deprecated statuses to the versions of any backend. Downloads are passed
+/// through untouched, so yanked versions stay installable when pinned explicitly.
+pub struct OverlayBackend {
+    inner: Arc<dyn Backend>,
+    overlay: Arc<VersionOverlay>,
+}
+
+impl OverlayBackend {
+    pub fn new(inner: Arc<dyn Backend>, overlay: Arc<VersionOverlay>) -> Self {
+        Self { inner, overlay }
+    }
+}
+
+impl Backend for OverlayBackend {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn health(&self) -> Result<()> {
+        self.inner.health()
+    }
+
+    fn list_provider_versions(
+        &self,
+        namespace: Namespace,
+        provider_type: ProviderType,
+    ) -> Result<Vec<VersionInfo>> {
+        self.list_provider_versions_with_freshness(namespace, provider_type)
+            .map(|(versions, _)| versions)
+    }
+
+    fn list_provider_versions_with_freshness(
+        &self,
+        namespace: Namespace,
+        provider_type: ProviderType,
+    ) -> Result<(Vec<VersionInfo>, Option<Freshness>)> {
+        let (versions, freshness) = self
+            .inner
+            .list_provider_versions_with_freshness(namespace.clone(), provider_type.clone())?;
+
+        let versions = versions
+            .into_iter()
+            .filter_map(|mut version| {
+                match self
+                    .overlay
+                    .status(&namespace, &provider_type, &version.version)
+                {
+                    Some(VersionStatus::Yanked) => return None,
+                    Some(VersionStatus::Deprecated { message }) => {
+                        version.deprecation = Some(message);
+                    }
+                    None => {}
+                }
+                Some(version)
+            })
+            .collect();
+
+        Ok((versions, freshness))
+    }
+
+    fn find_provider_package(
+        &self,
+        namespace: Namespace,
+        provider_type: ProviderType,
+        version: Version,
+        os: Os,
+        arch: Arch,
+    ) -> Result<Package> {
+        self.inner
+            .find_provider_package(namespace, provider_type, version, os, arch)
+    }
+
+    fn diagnose_provider(
+        &self,
+        namespace: Namespace,
+        provider_type: ProviderType,
+    ) -> Result<Vec<ReleaseDiagnostic>> {
+        self.inner.diagnose_provider(namespace, provider_type)
+    }
+
+    fn list_providers(&self) -> Result<Vec<ProviderRef>> {
+        self.inner.list_providers()
+    }
+
+    fn describe_provider(
+        &self,
+        namespace: Namespace,
+        provider_type: ProviderType,
+    ) -> Result<ProviderDetails> {
+        self.inner.describe_provider(namespace, provider_type)
+    }
+
+    fn refresh_provider(&self, namespace: Namespace, provider_type: ProviderType) -> Result<()> {
+        self.inner.refresh_provider(namespace, provider_type)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::overlay::OverlayConfig;
+    use crate::providers::FakeBackend;
+
+    #[test]
+    fn hides_yanked_and_annotates_deprecated_versions() {
+        let directory = tempfile::tempdir().unwrap();
+        let overlay = Arc::new(
+            VersionOverlay::open(&OverlayConfig {
+                path: directory.path().join("overlay.json"),
+            })
+            .unwrap(),
+        );
+        overlay
+            .set_status("hashicorp", "aws", "1.0.0", Some(VersionStatus::Yanked))
+            .unwrap();
+        overlay
+            .set_status(
+                "hashicorp",
+                "aws",
+                "0.9.0",
+                Some(VersionStatus::Deprecated {
+                    message: "upgrade to 1.x".to_string(),
+                }),
+            )
+            .unwrap();
+        let backend = OverlayBackend::new(Arc::new(FakeBackend), overlay);
+
+        let versions = backend
+            .list_provider_versions("hashicorp".parse().unwrap(), "aws".parse().unwrap())
+            .unwrap();
+        let yanked_package = backend.find_provider_package(
+            "hashicorp".parse().unwrap(),
+            "aws".parse().unwrap(),
+            "1.0.0".parse().unwrap(),
+            "linux".parse().unwrap(),
+            "amd64".parse().unwrap(),
+        );
+
+        assert_eq!(versions.len(), 1);
+        assert_eq!(versions[0].version, "0.9.0");
+        assert_eq!(versions[0].deprecation.as_deref(), Some("upgrade to 1.x"));
+        assert!(yanked_package.is_ok());
+    }
+}
@@ -0,0 +1,423 @@
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{Mutex, PoisonError};
+
+use crate::index::ProviderRef;
+use crate::params::{Arch, Namespace, Os, ProviderType, Version};
+use crate::publish::{ProviderRelease, ReleaseFile};
+use crate::types::{Package, SigningKeys, VersionInfo};
+
+use super::{Backend, ProviderBackendError, ProviderDetails, Result, WritableBackend};
+
+/// Flat blob storage that a `StoreBackend` lays providers out in. Keys are `/`-separated paths.
+pub trait ObjectStore: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn health(&self) -> Result<()>;
+
+    /// Returns `Ok(None)` when nothing is stored under `key`.
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>>;
+
+    fn put(&self, key: &str, contents: &[u8]) -> Result<()>;
+
+    /// Public URL Terraform downloads the object from.
+    fn url(&self, key: &str) -> String;
+}
+
+/// Serves providers published into an object store, laid out as:
+///
+/// - `providers.json`: every provider with a published version
+/// - `{namespace}/{type}/versions.json`: every published version
+/// - `{namespace}/{type}/{version}/release.json`: packages and signing keys of one version
+/// - `{namespace}/{type}/{version}/{filename}`: the uploaded release files
+pub struct StoreBackend<S> {
+    store: S,
+    /// Serializes the read-modify-write of `versions.json` and `providers.json` between publishes
+    /// from this process.
+    publishing: Mutex<()>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredRelease {
+    protocols: Vec<String>,
+    shasums_filename: String,
+    shasums_signature_filename: String,
+    signing_keys: SigningKeys,
+    packages: Vec<StoredPackage>,
+    /// RFC 3339 time of publishing; absent for releases published before it was recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    published_at: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredPackage {
+    os: String,
+    arch: String,
+    filename: String,
+    shasum: String,
+}
+
+impl<S: ObjectStore> StoreBackend<S> {
+    pub fn new(store: S) -> Self {
+        Self {
+            store,
+            publishing: Mutex::new(()),
+        }
+    }
+
+    fn read_json<T: serde::de::DeserializeOwned>(&self, key: &str) -> Result<Option<T>> {
+        match self.store.get(key)? {
+            Some(contents) => serde_json::from_slice(&contents)
+                .map(Some)
+                .map_err(ProviderBackendError::storage),
+            None => Ok(None),
+        }
+    }
+
+    fn write_json<T: serde::Serialize>(&self, key: &str, value: &T) -> Result<()> {
+        let contents = serde_json::to_vec_pretty(value).map_err(ProviderBackendError::storage)?;
+        self.store.put(key, &contents)
+    }
+}
+
+const CATALOG_KEY: &str = "providers.json";
+
+fn versions_key(namespace: &str, provider_type: &str) -> String {
+    format!("{namespace}/{provider_type}/versions.json")
+}
+
+fn release_key(namespace: &str, provider_type: &str, version: &str, filename: &str) -> String {
+    format!("{namespace}/{provider_type}/{version}/{filename}")
+}
+
+impl<S: ObjectStore> Backend for StoreBackend<S> {
+    fn name(&self) -> &'static str {
+        self.store.name()
+    }
+
+    fn health(&self) -> Result<()> {
+        self.store.health()
+    }
+
+    fn list_provider_versions(
+        &self,
+        namespace: Namespace,
+        provider_type: ProviderType,
+    ) -> Result<Vec<VersionInfo>> {
+        self.read_json(&versions_key(&namespace, &provider_type))?
+            .ok_or(ProviderBackendError::NotFound)
+    }
+
+    fn find_provider_package(
+        &self,
+        namespace: Namespace,
+        provider_type: ProviderType,
+        version: Version,
+        os: Os,
+        arch: Arch,
+    ) -> Result<Package> {
+        let release: StoredRelease = self
+            .read_json(&release_key(
+                &namespace,
+                &provider_type,
+                &version,
+                "release.json",
+            ))?
+            .ok_or(ProviderBackendError::NotFound)?;
+        let package = release
+            .packages
+            .into_iter()
+            .find(|package| package.os == *os && package.arch == *arch)
+            .ok_or_else(|| ProviderBackendError::PlatformUnsupported {
+                os: os.into(),
+                arch: arch.into(),
+            })?;
+        let url = |filename: &str| {
+            self.store
+                .url(&release_key(&namespace, &provider_type, &version, filename))
+        };
+
+        Ok(Package {
+            protocols: release.protocols,
+            download_url: url(&package.filename),
+            shasums_url: url(&release.shasums_filename),
+            shasums_signature_url: url(&release.shasums_signature_filename),
+            os: package.os,
+            arch: package.arch,
+            filename: package.filename,
+            shasum: package.shasum,
+            signing_keys: release.signing_keys,
+        })
+    }
+
+    fn list_providers(&self) -> Result<Vec<ProviderRef>> {
+        Ok(self.read_json(CATALOG_KEY)?.unwrap_or_default())
+    }
+
+    fn describe_provider(
+        &self,
+        namespace: Namespace,
+        provider_type: ProviderType,
+    ) -> Result<ProviderDetails> {
+        let versions = self.list_provider_versions(namespace.clone(), provider_type.clone())?;
+        let mut published = BTreeMap::new();
+        for version in versions {
+            let release: Option<StoredRelease> = self.read_json(&release_key(
+                &namespace,
+                &provider_type,
+                &version.version,
+                "release.json",
+            ))?;
+            if let Some(published_at) = release.and_then(|release| release.published_at) {
+                published.insert(version.version, published_at);
+            }
+        }
+
+        Ok(ProviderDetails {
+            published,
+            ..ProviderDetails::default()
+        })
+    }
+}
+
+impl<S: ObjectStore> WritableBackend for StoreBackend<S> {
+    fn publish_provider_version(&self, release: &ProviderRelease) -> Result<()> {
+        let _publishing = self
+            .publishing
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        let versions_key = versions_key(&release.namespace, &release.provider_type);
+        let mut versions: Vec<VersionInfo> = self.read_json(&versions_key)?.unwrap_or_default();
+        if versions.iter().any(|v| v.version == *release.version) {
+            return Err(ProviderBackendError::AlreadyExists);
+        }
+
+        let put = |file: &ReleaseFile| {
+            self.store.put(
+                &release_key(
+                    &release.namespace,
+                    &release.provider_type,
+                    &release.version,
+                    &file.filename,
+                ),
+                &file.contents,
+            )
+        };
+        for package in &release.packages {
+            put(&package.file)?;
+        }
+        put(&release.manifest)?;
+        put(&release.shasums)?;
+        put(&release.shasums_signature)?;
+
+        let stored = StoredRelease {
+            protocols: release.protocols.clone(),
+            shasums_filename: release.shasums.filename.clone(),
+            shasums_signature_filename: release.shasums_signature.filename.clone(),
+            signing_keys: SigningKeys {
+                gpg_public_keys: vec![release.signing_key.clone()],
+            },
+            packages: release
+                .packages
+                .iter()
+                .map(|package| StoredPackage {
+                    os: package.platform.os.clone(),
+                    arch: package.platform.arch.clone(),
+                    filename: package.file.filename.clone(),
+                    shasum: package.shasum.clone(),
+                })
+                .collect(),
+            published_at: Some(chrono::Utc::now().to_rfc3339()),
+        };
+        self.write_json(
+            &release_key(
+                &release.namespace,
+                &release.provider_type,
+                &release.version,
+                "release.json",
+            ),
+            &stored,
+        )?;
+
+        // Written last, so readers never see a version whose files are not all in place.
+        versions.push(release.version_info());
+        self.write_json(&versions_key, &versions)?;
+
+        let provider = ProviderRef {
+            namespace: release.namespace.clone(),
+            provider_type: release.provider_type.clone(),
+        };
+        let mut catalog: Vec<ProviderRef> = self.read_json(CATALOG_KEY)?.unwrap_or_default();
+        if !catalog.contains(&provider) {
+            catalog.push(provider);
+            self.write_json(CATALOG_KEY, &catalog)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::publish::ReleasePackage;
+    use crate::types::{GpgPublicKey, Platform};
+    use std::collections::HashMap;
+
+    #[derive(Default)]
+    struct MemoryStore {
+        objects: Mutex<HashMap<String, Vec<u8>>>,
+    }
+
+    impl ObjectStore for MemoryStore {
+        fn name(&self) -> &'static str {
+            "memory"
+        }
+
+        fn health(&self) -> Result<()> {
+            Ok(())
+        }
+
+        fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+            Ok(self.objects.lock().unwrap().get(key).cloned())
+        }
+
+        fn put(&self, key: &str, contents: &[u8]) -> Result<()> {
+            self.objects
+                .lock()
+                .unwrap()
+                .insert(key.to_string(), contents.to_vec());
+            Ok(())
+        }
+
+        fn url(&self, key: &str) -> String {
+            format!("https://downloads.example.com/{key}")
+        }
+    }
+
+    fn file(filename: &str) -> ReleaseFile {
+        ReleaseFile {
+            filename: filename.to_string(),
+            contents: filename.as_bytes().to_vec(),
+        }
+    }
+
+    fn release(version: &str) -> ProviderRelease {
+        let prefix = format!("terraform-provider-foo_{version}");
+        ProviderRelease {
+            namespace: "acme".parse().unwrap(),
+            provider_type: "foo".parse().unwrap(),
+            version: version.parse().unwrap(),
+            protocols: vec!["5.0".to_string()],
+            packages: vec![ReleasePackage {
+                platform: Platform {
+                    os: "linux".to_string(),
+                    arch: "amd64".to_string(),
+                },
+                shasum: "abc123".to_string(),
+                file: file(&format!("{prefix}_linux_amd64.zip")),
+            }],
+            shasums: file(&format!("{prefix}_SHA256SUMS")),
+            shasums_signature: file(&format!("{prefix}_SHA256SUMS.sig")),
+            manifest: file(&format!("{prefix}_manifest.json")),
+            signing_key: GpgPublicKey {
+                key_id: "ABCDEF0123456789".to_string(),
+                ascii_armor: "armor".to_string(),
+            },
+        }
+    }
+
+    #[test]
+    fn published_versions_are_served() {
+        let backend = StoreBackend::new(MemoryStore::default());
+        backend.publish_provider_version(&release("1.0.0")).unwrap();
+        backend.publish_provider_version(&release("1.1.0")).unwrap();
+
+        let versions = backend
+            .list_provider_versions("acme".parse().unwrap(), "foo".parse().unwrap())
+            .unwrap();
+        let package = backend
+            .find_provider_package(
+                "acme".parse().unwrap(),
+                "foo".parse().unwrap(),
+                "1.1.0".parse().unwrap(),
+                "linux".parse().unwrap(),
+                "amd64".parse().unwrap(),
+            )
+            .unwrap();
+
+        assert_eq!(versions.len(), 2);
+        assert_eq!(
+            package.download_url,
+            "https://downloads.example.com/acme/foo/1.1.0/terraform-provider-foo_1.1.0_linux_amd64.zip"
+        );
+        assert_eq!(
+            package.shasums_signature_url,
+            "https://downloads.example.com/acme/foo/1.1.0/terraform-provider-foo_1.1.0_SHA256SUMS.sig"
+        );
+        assert_eq!(
+            package.signing_keys.gpg_public_keys[0].key_id,
+            "ABCDEF0123456789"
+        );
+    }
+
+    #[test]
+    fn published_versions_are_immutable() {
+        let backend = StoreBackend::new(MemoryStore::default());
+        backend.publish_provider_version(&release("1.0.0")).unwrap();
+
+        assert!(matches!(
+            backend.publish_provider_version(&release("1.0.0")),
+            Err(ProviderBackendError::AlreadyExists)
+        ));
+    }
+
+    #[test]
+    fn unknown_providers_and_platforms_are_reported() {
+        let backend = StoreBackend::new(MemoryStore::default());
+        backend.publish_provider_version(&release("1.0.0")).unwrap();
+
+        assert!(matches!(
+            backend.list_provider_versions("acme".parse().unwrap(), "bar".parse().unwrap()),
+            Err(ProviderBackendError::NotFound)
+        ));
+        assert!(matches!(
+            backend.find_provider_package(
+                "acme".parse().unwrap(),
+                "foo".parse().unwrap(),
+                "1.0.0".parse().unwrap(),
+                "windows".parse().unwrap(),
+                "amd64".parse().unwrap(),
+            ),
+            Err(ProviderBackendError::PlatformUnsupported { .. })
+        ));
+    }
+
+    #[test]
+    fn published_providers_are_catalogued() {
+        let backend = StoreBackend::new(MemoryStore::default());
+        backend.publish_provider_version(&release("1.0.0")).unwrap();
+        backend.publish_provider_version(&release("1.1.0")).unwrap();
+
+        let providers = backend.list_providers().unwrap();
+        let details = backend
+            .describe_provider("acme".parse().unwrap(), "foo".parse().unwrap())
+            .unwrap();
+
+        assert_eq!(
+            providers,
+            vec![ProviderRef {
+                namespace: "acme".parse().unwrap(),
+                provider_type: "foo".parse().unwrap(),
+            }]
+        );
+        assert_eq!(
+            details.published.keys().collect::<Vec<_>>(),
+            vec!["1.0.0", "1.1.0"]
+        );
+        assert!(matches!(
+            backend.describe_provider("acme".parse().unwrap(), "bar".parse().unwrap()),
+            Err(ProviderBackendError::NotFound)
+        ));
+    }
+}
@@ -0,0 +1,647 @@
+use axum::{
+    Json,
+    extract::{Multipart, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use pgp::composed::{Deserializable, DetachedSignature, SignedPublicKey};
+use pgp::types::KeyDetails;
+use serde_derive::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Arc;
+use thiserror::Error;
+use tracing::{Span, info, warn};
+
+use crate::config::PlatformsConfig;
+use crate::package::{PackageCheckConfig, PackageError, check_package};
+use crate::params::{Namespace, ProviderType, ValidPath, Version};
+use crate::providers::{Backend, ProviderBackendError, WritableBackend};
+use crate::signing::{RegistrySigner, SigningConfig};
+use crate::types::{GpgPublicKey, Platform, VersionInfo};
+
+/// Multipart field carrying the ASCII-armored public key the checksums were signed with. Every
+/// other field is treated as a release file named by its filename.
+pub const SIGNING_KEY_FIELD: &str = "signing_key";
+
+/// Limits applied to provider uploads.
+#[derive(Deserialize, Serialize, PartialEq, Clone, Debug)]
+pub struct PublishConfig {
+    #[serde(default = "default_max_upload_bytes")]
+    pub max_upload_bytes: usize,
+    #[serde(default)]
+    pub signing: Option<SigningConfig>,
+    #[serde(default)]
+    pub packages: PackageCheckConfig,
+}
+
+fn default_max_upload_bytes() -> usize {
+    512 * 1024 * 1024
+}
+
+impl Default for PublishConfig {
+    fn default() -> Self {
+        Self {
+            max_upload_bytes: default_max_upload_bytes(),
+            signing: None,
+            packages: PackageCheckConfig::default(),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum PublishError {
+    #[error("`{0}` was uploaded more than once")]
+    DuplicateFile(String),
+    #[error("missing file `{0}`")]
+    MissingFile(String),
+    #[error(
+        "unexpected file `{0}`: packages must be named `{{name}}_{{version}}_{{os}}_{{arch}}.zip`"
+    )]
+    UnexpectedFile(String),
+    #[error("unsupported platform in `{0}`")]
+    UnsupportedPlatform(String),
+    #[error("no platform packages were uploaded")]
+    NoPackages,
+    #[error("SHA256SUMS is malformed: {0}")]
+    InvalidChecksums(String),
+    #[error("`{0}` is not listed in SHA256SUMS")]
+    NotInChecksums(String),
+    #[error("checksum of `{0}` does not match SHA256SUMS")]
+    ChecksumMismatch(String),
+    #[error("manifest is malformed: {0}")]
+    InvalidManifest(String),
+    #[error("missing `{SIGNING_KEY_FIELD}` field")]
+    MissingSigningKey,
+    #[error("signing key is not a valid ASCII-armored OpenPGP public key")]
+    InvalidSigningKey,
+    #[error("SHA256SUMS signature is not a valid OpenPGP signature")]
+    InvalidSignature,
+    #[error("SHA256SUMS signature was not made by the signing key")]
+    SignatureMismatch,
+    #[error("`{filename}` {source}")]
+    InvalidPackage {
+        filename: String,
+        source: Box<PackageError>,
+    },
+    #[error("`{0}` must not be uploaded: releases in this namespace are signed by the registry")]
+    SignedByRegistry(String),
+    #[error("the registry could not sign the release")]
+    SigningFailed(#[source] pgp::errors::Error),
+    #[error(transparent)]
+    Backend(#[from] ProviderBackendError),
+}
+
+impl IntoResponse for PublishError {
+    fn into_response(self) -> Response {
+        match self {
+            Self::Backend(error) => error.into_response(),
+            error => {
+                let status = if let Self::SigningFailed(cause) = &error {
+                    warn!(%cause, "Signing a release failed");
+                    StatusCode::INTERNAL_SERVER_ERROR
+                } else {
+                    StatusCode::BAD_REQUEST
+                };
+                (
+                    status,
+                    Json(serde_json::json!({ "errors": [error.to_string()] })),
+                )
+                    .into_response()
+            }
+        }
+    }
+}
+
+/// A file as uploaded, before validation.
+#[derive(Debug, Clone)]
+pub struct ReleaseFile {
+    pub filename: String,
+    pub contents: Vec<u8>,
+}
+
+#[derive(Debug, Default)]
+pub struct Upload {
+    pub files: Vec<ReleaseFile>,
+    pub signing_key: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReleasePackage {
+    pub platform: Platform,
+    pub shasum: String,
+    pub file: ReleaseFile,
+}
+
+/// A provider version whose files have been checked against its signed SHA256SUMS and are ready
+/// to be stored.
+#[derive(Debug, Clone)]
+pub struct ProviderRelease {
+    pub namespace: Namespace,
+    pub provider_type: ProviderType,
+    pub version: Version,
+    pub protocols: Vec<String>,
+    pub packages: Vec<ReleasePackage>,
+    pub shasums: ReleaseFile,
+    pub shasums_signature: ReleaseFile,
+    pub manifest: ReleaseFile,
+    pub signing_key: GpgPublicKey,
+}
+
+/// The subset of the `terraform-provider-{type}_{version}_manifest.json` file the registry uses.
+#[derive(Deserialize)]
+struct Manifest {
+    #[serde(default)]
+    metadata: ManifestMetadata,
+}
+
+#[derive(Deserialize, Default)]
+struct ManifestMetadata {
+    #[serde(default)]
+    protocol_versions: Vec<String>,
+}
+
+impl ProviderRelease {
+    /// Checks that the upload is a complete, correctly named and correctly signed release of
+    /// `namespace/provider_type` at `version`, whose packages are for accepted `platforms` and
+    /// pass `checks`. Uploads to namespaces the `signer` signs for are signed by it first.
+    pub fn validate(
+        namespace: Namespace,
+        provider_type: ProviderType,
+        version: Version,
+        mut upload: Upload,
+        signer: Option<&RegistrySigner>,
+        checks: &PackageCheckConfig,
+        platforms: &PlatformsConfig,
+    ) -> Result<Self, PublishError> {
+        let prefix = format!("terraform-provider-{provider_type}_{version}");
+        if let Some(signer) = signer.filter(|signer| signer.signs_for(&namespace)) {
+            signer.complete(&prefix, &mut upload)?;
+        }
+        let mut files = HashMap::new();
+        for file in upload.files {
+            if files.contains_key(&file.filename) {
+                return Err(PublishError::DuplicateFile(file.filename));
+            }
+            files.insert(file.filename.clone(), file);
+        }
+        let mut take = |filename: String| {
+            files
+                .remove(&filename)
+                .ok_or(PublishError::MissingFile(filename))
+        };
+        let shasums = take(format!("{prefix}_SHA256SUMS"))?;
+        let shasums_signature = take(format!("{prefix}_SHA256SUMS.sig"))?;
+        let manifest = take(format!("{prefix}_manifest.json"))?;
+
+        let armored_key = upload.signing_key.ok_or(PublishError::MissingSigningKey)?;
+        let signing_key =
+            verify_signature(&armored_key, &shasums_signature.contents, &shasums.contents)?;
+
+        let mut checksums = parse_shasums(&shasums.contents)?;
+        let mut verify_checksum = |file: &ReleaseFile| {
+            let expected = checksums
+                .remove(&file.filename)
+                .ok_or_else(|| PublishError::NotInChecksums(file.filename.clone()))?;
+            if sha256_hex(&file.contents) != expected {
+                return Err(PublishError::ChecksumMismatch(file.filename.clone()));
+            }
+            Ok(expected)
+        };
+
+        verify_checksum(&manifest)?;
+        let protocols = parse_manifest(&manifest.contents)?;
+
+        let mut packages = files
+            .into_values()
+            .map(|file| {
+                let platform = package_platform(&prefix, &file.filename, platforms)?;
+                let shasum = verify_checksum(&file)?;
+                check_package(&file.contents, &provider_type, &version, &platform, checks)
+                    .map_err(|source| PublishError::InvalidPackage {
+                        filename: file.filename.clone(),
+                        source: Box::new(source),
+                    })?;
+                Ok(ReleasePackage {
+                    platform,
+                    shasum,
+                    file,
+                })
+            })
+            .collect::<Result<Vec<_>, PublishError>>()?;
+        if let Some(filename) = checksums.into_keys().min() {
+            return Err(PublishError::MissingFile(filename));
+        }
+        if packages.is_empty() {
+            return Err(PublishError::NoPackages);
+        }
+        packages.sort_by(|a, b| a.file.filename.cmp(&b.file.filename));
+
+        Ok(Self {
+            namespace,
+            provider_type,
+            version,
+            protocols,
+            packages,
+            shasums,
+            shasums_signature,
+            manifest,
+            signing_key,
+        })
+    }
+
+    pub fn version_info(&self) -> VersionInfo {
+        VersionInfo {
+            version: self.version.to_string(),
+            protocols: self.protocols.clone(),
+            platforms: self
+                .packages
+                .iter()
+                .map(|package| package.platform.clone())
+                .collect(),
+            deprecation: None,
+        }
+    }
+}
+
+pub fn sha256_hex(contents: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(contents))
+}
+
+/// Parses `sha256sum` output into a map of filename to lowercase hex digest.
+fn parse_shasums(contents: &[u8]) -> Result<HashMap<String, String>, PublishError> {
+    let text = std::str::from_utf8(contents)
+        .map_err(|_| PublishError::InvalidChecksums("not UTF-8".to_string()))?;
+
+    let mut checksums = HashMap::new();
+    for line in text.lines().filter(|line| !line.trim().is_empty()) {
+        let (digest, filename) = line
+            .split_once(char::is_whitespace)
+            .ok_or_else(|| PublishError::InvalidChecksums(format!("invalid line `{line}`")))?;
+        // `sha256sum --binary` marks filenames with a leading `*`.
+        let filename = filename.trim_start().trim_start_matches('*');
+        if digest.len() != 64 || !digest.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(PublishError::InvalidChecksums(format!(
+                "invalid digest for `{filename}`"
+            )));
+        }
+        checksums.insert(filename.to_string(), digest.to_ascii_lowercase());
+    }
+
+    Ok(checksums)
+}
+
+fn parse_manifest(contents: &[u8]) -> Result<Vec<String>, PublishError> {
+    let manifest: Manifest = serde_json::from_slice(contents)
+        .map_err(|error| PublishError::InvalidManifest(error.to_string()))?;
+
+    let protocols = manifest.metadata.protocol_versions;
+    if protocols.is_empty() {
+        return Ok(vec!["5.0".to_string()]);
+    }
+    Ok(protocols)
+}
+
+/// Extracts the platform from a `{prefix}_{os}_{arch}.zip` filename.
+fn package_platform(
+    prefix: &str,
+    filename: &str,
+    platforms: &PlatformsConfig,
+) -> Result<Platform, PublishError> {
+    let (os, arch) = filename
+        .strip_prefix(prefix)
+        .and_then(|rest| rest.strip_prefix('_'))
+        .and_then(|rest| rest.strip_suffix(".zip"))
+        .and_then(|rest| rest.split_once('_'))
+        .ok_or_else(|| PublishError::UnexpectedFile(filename.to_string()))?;
+
+    if !platforms.accepts_os(os) || !platforms.accepts_arch(arch) {
+        return Err(PublishError::UnsupportedPlatform(filename.to_string()));
+    }
+    Ok(Platform {
+        os: os.to_string(),
+        arch: arch.to_string(),
+    })
+}
+
+/// Checks the detached signature over `signed` against the primary key or any subkey of
+/// `armored_key`, returning the key as it will be advertised to Terraform.
+fn verify_signature(
+    armored_key: &str,
+    signature: &[u8],
+    signed: &[u8],
+) -> Result<GpgPublicKey, PublishError> {
+    let (key, _) =
+        SignedPublicKey::from_string(armored_key).map_err(|_| PublishError::InvalidSigningKey)?;
+    let (signature, _) = DetachedSignature::from_reader_single(signature)
+        .map_err(|_| PublishError::InvalidSignature)?;
+
+    let verified = signature.verify(&key, signed).is_ok()
+        || key
+            .public_subkeys
+            .iter()
+            .any(|subkey| signature.verify(subkey, signed).is_ok());
+    if !verified {
+        return Err(PublishError::SignatureMismatch);
+    }
+
+    Ok(GpgPublicKey {
+        key_id: key.legacy_key_id().to_string().to_ascii_uppercase(),
+        ascii_armor: armored_key.to_string(),
+    })
+}
+
+/// Accepts a provider version as a multipart upload of its packages, SHA256SUMS, signature and
+/// manifest, and stores it through the writable backend
+pub async fn publish_handler(
+    State(backend): State<Arc<dyn Backend>>,
+    State(publisher): State<Option<Arc<dyn WritableBackend>>>,
+    State(signer): State<Option<Arc<RegistrySigner>>>,
+    State(config): State<PublishConfig>,
+    State(platforms): State<PlatformsConfig>,
+    ValidPath((namespace, provider_type, version)): ValidPath<(Namespace, ProviderType, Version)>,
+    mut multipart: Multipart,
+) -> Response {
+    let Some(publisher) = publisher else {
+        return ProviderBackendError::NotFound.into_response();
+    };
+    Span::current()
+        .record("namespace", namespace.as_str())
+        .record("provider_type", provider_type.as_str())
+        .record("version", version.as_str());
+
+    let mut upload = Upload::default();
+    loop {
+        let field = match multipart.next_field().await {
+            Ok(Some(field)) => field,
+            Ok(None) => break,
+            Err(error) => return error.into_response(),
+        };
+        let name = field.name().unwrap_or_default().to_string();
+        let filename = field
+            .file_name()
+            .map_or_else(|| name.clone(), str::to_string);
+        let contents = match field.bytes().await {
+            Ok(contents) => contents.to_vec(),
+            Err(error) => return error.into_response(),
+        };
+
+        if name == SIGNING_KEY_FIELD {
+            let Ok(armored_key) = String::from_utf8(contents) else {
+                return PublishError::InvalidSigningKey.into_response();
+            };
+            upload.signing_key = Some(armored_key);
+        } else {
+            upload.files.push(ReleaseFile { filename, contents });
+        }
+    }
+
+    let span = Span::current();
+    let result = tokio::task::spawn_blocking(move || {
+        span.in_scope(|| {
+            let release = ProviderRelease::validate(
+                namespace,
+                provider_type,
+                version,
+                upload,
+                signer.as_deref(),
+                &config.packages,
+                &platforms,
+            )?;
+            publisher.publish_provider_version(&release)?;
+            // The version is stored; failing to refresh only delays when it is listed.
+            if let Err(error) =
+                backend.refresh_provider(release.namespace.clone(), release.provider_type.clone())
+            {
+                warn!(
+                    %error,
+                    "Published version may not be listed until the index syncs or the cache expires"
+                );
+            }
+            Ok::<_, PublishError>(release)
+        })
+    })
+    .await;
+
+    match result {
+        Ok(Ok(release)) => {
+            info!(
+                namespace = %release.namespace,
+                provider_type = %release.provider_type,
+                version = %release.version,
+                "Provider version published"
+            );
+            (StatusCode::CREATED, Json(release.version_info())).into_response()
+        }
+        Ok(Err(error)) => error.into_response(),
+        Err(error) => ProviderBackendError::storage(error).into_response(),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::package::tests::{executable, provider_zip, zip};
+    use pgp::composed::{KeyType, SecretKeyParamsBuilder, SignedSecretKey};
+    use pgp::crypto::hash::HashAlgorithm;
+    use pgp::ser::Serialize as _;
+    use pgp::types::Password;
+    use std::fmt::Write;
+
+    pub fn secret_key() -> SignedSecretKey {
+        SecretKeyParamsBuilder::default()
+            .key_type(KeyType::Ed25519)
+            .can_sign(true)
+            .primary_user_id("Registry Test <test@example.com>".into())
+            .build()
+            .unwrap()
+            .generate(rand::thread_rng())
+            .unwrap()
+    }
+
+    fn sign(key: &SignedSecretKey, data: &[u8]) -> Vec<u8> {
+        DetachedSignature::sign_binary_data(
+            rand::thread_rng(),
+            &key.primary_key,
+            &Password::empty(),
+            HashAlgorithm::Sha256,
+            data,
+        )
+        .unwrap()
+        .to_bytes()
+        .unwrap()
+    }
+
+    fn file(filename: &str, contents: &[u8]) -> ReleaseFile {
+        ReleaseFile {
+            filename: filename.to_string(),
+            contents: contents.to_vec(),
+        }
+    }
+
+    /// A correctly signed upload of `acme/foo` 1.0.0 for linux/amd64 and darwin/arm64.
+    pub fn signed_upload(key: &SignedSecretKey) -> Upload {
+        let packages = [
+            file(
+                "terraform-provider-foo_1.0.0_linux_amd64.zip",
+                &provider_zip("foo", "1.0.0", "linux", "amd64"),
+            ),
+            file(
+                "terraform-provider-foo_1.0.0_darwin_arm64.zip",
+                &provider_zip("foo", "1.0.0", "darwin", "arm64"),
+            ),
+        ];
+        sign_packages(key, &packages)
+    }
+
+    /// Adds a manifest, SHA256SUMS and its signature by `key` to `packages` of `acme/foo` 1.0.0.
+    fn sign_packages(key: &SignedSecretKey, packages: &[ReleaseFile]) -> Upload {
+        let manifest = file(
+            "terraform-provider-foo_1.0.0_manifest.json",
+            br#"{"version":1,"metadata":{"protocol_versions":["6.0"]}}"#,
+        );
+        let mut shasums = String::new();
+        for f in packages.iter().chain([&manifest]) {
+            writeln!(shasums, "{}  {}", sha256_hex(&f.contents), f.filename).unwrap();
+        }
+        let signature = sign(key, shasums.as_bytes());
+
+        let mut files = packages.to_vec();
+        files.extend([
+            manifest,
+            file(
+                "terraform-provider-foo_1.0.0_SHA256SUMS",
+                shasums.as_bytes(),
+            ),
+            file("terraform-provider-foo_1.0.0_SHA256SUMS.sig", &signature),
+        ]);
+        Upload {
+            files,
+            signing_key: Some(key.to_public_key().to_armored_string(None.into()).unwrap()),
+        }
+    }
+
+    fn validate(upload: Upload) -> Result<ProviderRelease, PublishError> {
+        ProviderRelease::validate(
+            "acme".parse().unwrap(),
+            "foo".parse().unwrap(),
+            "1.0.0".parse().unwrap(),
+            upload,
+            None,
+            &PackageCheckConfig::default(),
+            &PlatformsConfig::default(),
+        )
+    }
+
+    fn replace(upload: &mut Upload, filename: &str, contents: &[u8]) {
+        let file = upload
+            .files
+            .iter_mut()
+            .find(|file| file.filename == filename)
+            .unwrap();
+        file.contents = contents.to_vec();
+    }
+
+    #[test]
+    fn accepts_a_signed_release() {
+        let key = secret_key();
+
+        let release = validate(signed_upload(&key)).unwrap();
+
+        assert_eq!(release.protocols, vec!["6.0"]);
+        assert_eq!(release.packages.len(), 2);
+        assert_eq!(release.packages[0].platform.os, "darwin");
+        assert_eq!(release.packages[1].platform.arch, "amd64");
+        assert_eq!(
+            release.signing_key.key_id,
+            key.legacy_key_id().to_string().to_ascii_uppercase()
+        );
+    }
+
+    #[test]
+    fn rejects_tampered_package() {
+        let mut upload = signed_upload(&secret_key());
+        replace(
+            &mut upload,
+            "terraform-provider-foo_1.0.0_linux_amd64.zip",
+            b"tampered",
+        );
+
+        assert!(matches!(
+            validate(upload),
+            Err(PublishError::ChecksumMismatch(filename))
+                if filename == "terraform-provider-foo_1.0.0_linux_amd64.zip"
+        ));
+    }
+
+    #[test]
+    fn rejects_signature_from_another_key() {
+        let mut upload = signed_upload(&secret_key());
+        upload.signing_key = Some(
+            secret_key()
+                .to_public_key()
+                .to_armored_string(None.into())
+                .unwrap(),
+        );
+
+        assert!(matches!(
+            validate(upload),
+            Err(PublishError::SignatureMismatch)
+        ));
+    }
+
+    #[test]
+    fn rejects_badly_named_packages() {
+        let mut upload = signed_upload(&secret_key());
+        upload
+            .files
+            .push(file("terraform-provider-foo_linux_amd64.zip", b"linux"));
+
+        assert!(matches!(
+            validate(upload),
+            Err(PublishError::UnexpectedFile(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_missing_package_listed_in_checksums() {
+        let mut upload = signed_upload(&secret_key());
+        upload
+            .files
+            .retain(|file| file.filename != "terraform-provider-foo_1.0.0_darwin_arm64.zip");
+
+        assert!(matches!(
+            validate(upload),
+            Err(PublishError::MissingFile(filename))
+                if filename == "terraform-provider-foo_1.0.0_darwin_arm64.zip"
+        ));
+    }
+
+    #[test]
+    fn rejects_packages_built_for_another_platform() {
+        let key = secret_key();
+        let upload = sign_packages(
+            &key,
+            &[file(
+                "terraform-provider-foo_1.0.0_linux_arm64.zip",
+                &zip(&[(
+                    "terraform-provider-foo_v1.0.0",
+                    &executable("linux", "amd64"),
+                )]),
+            )],
+        );
+
+        let error = validate(upload).unwrap_err();
+
+        assert!(matches!(
+            &error,
+            PublishError::InvalidPackage { source, .. }
+                if matches!(**source, PackageError::PlatformMismatch { .. })
+        ));
+        assert_eq!(
+            error.to_string(),
+            "`terraform-provider-foo_1.0.0_linux_arm64.zip` contains \
+             `terraform-provider-foo_v1.0.0` built for ELF amd64, not linux_arm64"
+        );
+    }
+}
@@ -1,16 +1,227 @@
 use axum::{
     Json, Router,
-    extract::{Path, State},
-    http::StatusCode,
-    response::IntoResponse,
-    routing::get,
+    extract::{DefaultBodyLimit, FromRef, Query, Request, State},
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, put},
 };
+use serde_derive::Deserialize;
+use std::path::PathBuf;
 use std::sync::Arc;
-use tower_http::trace::TraceLayer;
-use tracing::info;
+use std::time::{Duration, Instant};
+use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
+use tower_http::services::ServeDir;
+use tower_http::trace::{DefaultOnResponse, TraceLayer};
+use tracing::{Level, Span, info};
 
-use crate::providers::Backend;
-use crate::types::{ServiceDiscovery, VersionsResponse};
+use crate::auth::{self, AuthConfig};
+use crate::config::PlatformsConfig;
+use crate::constraint::VersionQuery;
+use crate::discovery;
+use crate::http_cache::{HttpCacheConfig, conditional_json};
+use crate::index::MetadataIndex;
+use crate::listing::{self, ListingConfig};
+use crate::metrics::{self, Metrics};
+use crate::overlay::{self, VersionOverlay};
+use crate::params::{Arch, Namespace, Os, ProviderType, ValidPath, Version};
+use crate::providers::{
+    Backend, Freshness, PARTIAL_SUFFIX, ProviderBackendError, Staleness, WritableBackend,
+};
+use crate::publish::{self, PublishConfig};
+use crate::search;
+use crate::shutdown::Shutdown;
+use crate::signing::RegistrySigner;
+use crate::telemetry;
+use crate::types::{
+    BackendStatus, DiagnosticsResponse, ReadinessResponse, ServiceDiscovery, VersionsResponse,
+};
+
+/// Shared state handed to every route
+#[derive(Clone)]
+pub struct AppState {
+    pub backend: Arc<dyn Backend>,
+    pub shutdown: Shutdown,
+    pub metrics: Metrics,
+    pub http_cache: HttpCacheConfig,
+    pub listing: ListingConfig,
+    pub index: Option<Arc<MetadataIndex>>,
+    pub overlay: Option<Arc<VersionOverlay>>,
+    pub publisher: Option<Arc<dyn WritableBackend>>,
+    pub auth: AuthConfig,
+    pub publish: PublishConfig,
+    pub platforms: PlatformsConfig,
+    pub signer: Option<Arc<RegistrySigner>>,
+    /// Directory served at `/files`, holding packages published to the filesystem backend.
+    pub files: Option<PathBuf>,
+}
+
+impl AppState {
+    pub fn new(backend: Arc<dyn Backend>, metrics: Metrics) -> Self {
+        Self {
+            backend,
+            shutdown: Shutdown::default(),
+            metrics,
+            http_cache: HttpCacheConfig::default(),
+            listing: ListingConfig::default(),
+            index: None,
+            overlay: None,
+            publisher: None,
+            auth: AuthConfig::default(),
+            publish: PublishConfig::default(),
+            platforms: PlatformsConfig::default(),
+            signer: None,
+            files: None,
+        }
+    }
+
+    #[must_use]
+    pub fn with_shutdown(mut self, shutdown: Shutdown) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    #[must_use]
+    pub fn with_http_cache(mut self, http_cache: HttpCacheConfig) -> Self {
+        self.http_cache = http_cache;
+        self
+    }
+
+    #[must_use]
+    pub fn with_listing(mut self, listing: ListingConfig) -> Self {
+        self.listing = listing;
+        self
+    }
+
+    #[must_use]
+    pub fn with_index(mut self, index: Arc<MetadataIndex>) -> Self {
+        self.index = Some(index);
+        self
+    }
+
+    #[must_use]
+    pub fn with_overlay(mut self, overlay: Arc<VersionOverlay>) -> Self {
+        self.overlay = Some(overlay);
+        self
+    }
+
+    /// Tokens accepted by the `/admin` routes.
+    #[must_use]
+    pub fn with_auth(mut self, auth: AuthConfig) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    #[must_use]
+    pub fn with_publisher(
+        mut self,
+        publisher: Arc<dyn WritableBackend>,
+        publish: PublishConfig,
+    ) -> Self {
+        self.publisher = Some(publisher);
+        self.publish = publish;
+        self
+    }
+
+    #[must_use]
+    pub fn with_platforms(mut self, platforms: PlatformsConfig) -> Self {
+        self.platforms = platforms;
+        self
+    }
+
+    #[must_use]
+    pub fn with_signer(mut self, signer: Arc<RegistrySigner>) -> Self {
+        self.signer = Some(signer);
+        self
+    }
+
+    #[must_use]
+    pub fn with_files(mut self, root: PathBuf) -> Self {
+        self.files = Some(root);
+        self
+    }
+}
+
+impl FromRef<AppState> for Arc<dyn Backend> {
+    fn from_ref(state: &AppState) -> Self {
+        state.backend.clone()
+    }
+}
+
+impl FromRef<AppState> for Shutdown {
+    fn from_ref(state: &AppState) -> Self {
+        state.shutdown.clone()
+    }
+}
+
+impl FromRef<AppState> for Metrics {
+    fn from_ref(state: &AppState) -> Self {
+        state.metrics.clone()
+    }
+}
+
+impl FromRef<AppState> for HttpCacheConfig {
+    fn from_ref(state: &AppState) -> Self {
+        state.http_cache.clone()
+    }
+}
+
+impl FromRef<AppState> for ListingConfig {
+    fn from_ref(state: &AppState) -> Self {
+        state.listing.clone()
+    }
+}
+
+impl FromRef<AppState> for Option<Arc<MetadataIndex>> {
+    fn from_ref(state: &AppState) -> Self {
+        state.index.clone()
+    }
+}
+
+impl FromRef<AppState> for Option<Arc<VersionOverlay>> {
+    fn from_ref(state: &AppState) -> Self {
+        state.overlay.clone()
+    }
+}
+
+impl FromRef<AppState> for Option<Arc<dyn WritableBackend>> {
+    fn from_ref(state: &AppState) -> Self {
+        state.publisher.clone()
+    }
+}
+
+impl FromRef<AppState> for PublishConfig {
+    fn from_ref(state: &AppState) -> Self {
+        state.publish.clone()
+    }
+}
+
+impl FromRef<AppState> for PlatformsConfig {
+    fn from_ref(state: &AppState) -> Self {
+        state.platforms.clone()
+    }
+}
+
+impl FromRef<AppState> for Option<Arc<RegistrySigner>> {
+    fn from_ref(state: &AppState) -> Self {
+        state.signer.clone()
+    }
+}
+
+/// Runs a blocking backend call on the blocking thread pool, inside the current request span and
+/// with the current subscriber, so spans the backend opens still join the request's trace.
+pub(crate) async fn call_backend<T: Send + 'static>(
+    backend: Arc<dyn Backend>,
+    call: impl FnOnce(&dyn Backend) -> Result<T, ProviderBackendError> + Send + 'static,
+) -> Result<T, ProviderBackendError> {
+    let span = Span::current();
+    let dispatch = tracing::dispatcher::get_default(Clone::clone);
+    tokio::task::spawn_blocking(move || {
+        tracing::dispatcher::with_default(&dispatch, || span.in_scope(|| call(backend.as_ref())))
+    })
+    .await
+    .unwrap_or_else(|error| Err(ProviderBackendError::storage(error)))
+}
 
 /// Service discovery endpoint - returns registry metadata
 async fn service_discovery() -> impl IntoResponse {
@@ -23,61 +234,1166 @@ async fn service_discovery() -> impl IntoResponse {
     Json(response)
 }
 
-/// List available versions for a provider
+/// Query parameters accepted by the version listing on top of the registry protocol
+#[derive(Debug, Deserialize)]
+struct VersionsQuery {
+    /// A Terraform version constraint such as `~> 1.2, != 1.2.5`, or `latest`.
+    constraint: Option<String>,
+}
+
+/// List available versions for a provider in semver order, optionally only those matching a
+/// constraint
 async fn list_versions(
     State(backend): State<Arc<dyn Backend>>,
-    Path((namespace, provider_type)): Path<(String, String)>,
+    State(http_cache): State<HttpCacheConfig>,
+    State(listing): State<ListingConfig>,
+    headers: HeaderMap,
+    ValidPath((namespace, provider_type)): ValidPath<(Namespace, ProviderType)>,
+    Query(query): Query<VersionsQuery>,
 ) -> impl IntoResponse {
-    info!("Versions requested for {}/{}", namespace, provider_type);
+    Span::current()
+        .record("namespace", namespace.as_str())
+        .record("provider_type", provider_type.as_str());
+    info!(%namespace, %provider_type, constraint = ?query.constraint, "Versions requested");
+
+    let selection = match query.constraint.as_deref().map(str::parse::<VersionQuery>) {
+        None => None,
+        Some(Ok(selection)) => Some(selection),
+        Some(Err(error)) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({ "errors": [error.to_string()] })),
+            )
+                .into_response();
+        }
+    };
 
-    match backend.list_provider_versions(namespace, provider_type) {
-        Ok(versions) => Json(VersionsResponse { versions }).into_response(),
+    let provider = format!("{namespace}/{provider_type}");
+    match call_backend(backend, move |backend| {
+        backend.list_provider_versions_with_freshness(namespace, provider_type)
+    })
+    .await
+    {
+        Ok((versions, freshness)) => {
+            let versions = listing::normalize(versions, &listing);
+            let (versions, latest) = match &selection {
+                Some(selection) => selection.select(versions),
+                None => (versions, None),
+            };
+            let warnings = versions
+                .iter()
+                .filter_map(|version| {
+                    let message = version.deprecation.as_ref()?;
+                    Some(format!(
+                        "{provider} {} is deprecated: {message}",
+                        version.version
+                    ))
+                })
+                .collect();
+            let mut response = conditional_json(
+                &headers,
+                &VersionsResponse {
+                    versions,
+                    warnings,
+                    latest,
+                },
+                &http_cache.versions,
+            );
+            if let Some(freshness) = freshness {
+                set_freshness_headers(response.headers_mut(), freshness);
+            }
+            response
+        }
         Err(error) => error.into_response(),
     }
 }
 
+/// Marks responses served from the cache with `Age`, plus a `Warning` when they are stale
+fn set_freshness_headers(headers: &mut HeaderMap, freshness: Freshness) {
+    headers.insert(header::AGE, HeaderValue::from(freshness.age.as_secs()));
+
+    let warning = match freshness.staleness {
+        Staleness::Fresh => return,
+        Staleness::Revalidating => r#"110 - "Response is Stale""#,
+        Staleness::RevalidationFailed => r#"111 - "Revalidation Failed""#,
+    };
+    headers.insert(header::WARNING, HeaderValue::from_static(warning));
+}
+
 /// Find a provider package for download
 async fn find_provider_package(
     State(backend): State<Arc<dyn Backend>>,
-    Path((namespace, provider_type, version, os, arch)): Path<(
-        String,
-        String,
-        String,
-        String,
-        String,
+    State(metrics): State<Metrics>,
+    State(http_cache): State<HttpCacheConfig>,
+    headers: HeaderMap,
+    ValidPath((namespace, provider_type, version, os, arch)): ValidPath<(
+        Namespace,
+        ProviderType,
+        Version,
+        Os,
+        Arch,
     )>,
 ) -> impl IntoResponse {
-    info!(
-        "Download requested for {}/{} version {} on {}/{}",
-        namespace, provider_type, version, os, arch
+    Span::current()
+        .record("namespace", namespace.as_str())
+        .record("provider_type", provider_type.as_str())
+        .record("version", version.as_str())
+        .record("os", os.as_str())
+        .record("arch", arch.as_str());
+    info!(%namespace, %provider_type, %version, %os, %arch, "Download requested");
+
+    let lookup = (
+        namespace.clone(),
+        provider_type.clone(),
+        version.clone(),
+        os.clone(),
+        arch.clone(),
     );
+    match call_backend(backend, move |backend| {
+        let (namespace, provider_type, version, os, arch) = lookup;
+        backend.find_provider_package(namespace, provider_type, version, os, arch)
+    })
+    .await
+    {
+        Ok(package) => {
+            metrics.record_download(&namespace, &provider_type, &version, &os, &arch);
+            conditional_json(&headers, &package, &http_cache.download)
+        }
+        Err(error) => error.into_response(),
+    }
+}
 
-    match backend.find_provider_package(namespace, provider_type, version, os, arch) {
-        Ok(package) => Json(package).into_response(),
+/// List every release the backend holds for a provider, with why each was accepted or skipped
+async fn diagnose_provider(
+    State(backend): State<Arc<dyn Backend>>,
+    ValidPath((namespace, provider_type)): ValidPath<(Namespace, ProviderType)>,
+) -> impl IntoResponse {
+    match call_backend(backend, move |backend| {
+        backend.diagnose_provider(namespace, provider_type)
+    })
+    .await
+    {
+        Ok(releases) => Json(DiagnosticsResponse { releases }).into_response(),
         Err(error) => error.into_response(),
     }
 }
 
-/// Health check endpoint
-async fn health_check() -> impl IntoResponse {
+/// List every provider in the metadata index, across all namespaces
+async fn list_indexed_providers(
+    State(index): State<Option<Arc<MetadataIndex>>>,
+) -> impl IntoResponse {
+    let Some(index) = index else {
+        return ProviderBackendError::NotFound.into_response();
+    };
+
+    match tokio::task::spawn_blocking(move || index.providers()).await {
+        Ok(Ok(providers)) => Json(providers).into_response(),
+        Ok(Err(error)) => ProviderBackendError::storage(error).into_response(),
+        Err(error) => ProviderBackendError::storage(error).into_response(),
+    }
+}
+
+/// Health check endpoint - reports not-ready once the server starts draining
+async fn health_check(State(shutdown): State<Shutdown>) -> impl IntoResponse {
+    if shutdown.is_draining() {
+        return (StatusCode::SERVICE_UNAVAILABLE, "DRAINING");
+    }
+
     (StatusCode::OK, "OK")
 }
 
+/// How long `/ready` waits for the backend's health check before reporting it not ready.
+const READINESS_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Readiness endpoint - probes the configured backend so orchestrators can stop routing to an
+/// instance that cannot serve
+async fn readiness_check(
+    State(backend): State<Arc<dyn Backend>>,
+    State(shutdown): State<Shutdown>,
+) -> impl IntoResponse {
+    let status = probe_backend(backend, READINESS_TIMEOUT).await;
+
+    let response = ReadinessResponse {
+        ready: status.ready && !shutdown.is_draining(),
+        backends: vec![status],
+    };
+    let code = if response.ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (code, Json(response))
+}
+
+/// Runs the backend's health check, giving up after `timeout` so a hung upstream makes the
+/// instance not ready rather than hanging the probe.
+async fn probe_backend(backend: Arc<dyn Backend>, timeout: Duration) -> BackendStatus {
+    let started = Instant::now();
+    let probe = backend.clone();
+    let span = tracing::Span::current();
+    let result = tokio::time::timeout(
+        timeout,
+        tokio::task::spawn_blocking(move || span.in_scope(|| probe.health())),
+    )
+    .await;
+    let latency_ms = u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX);
+
+    let error = match result {
+        Ok(Ok(Ok(()))) => None,
+        Ok(Ok(Err(error))) => Some(error.to_string()),
+        Ok(Err(error)) => Some(error.to_string()),
+        Err(_) => Some(format!(
+            "health check timed out after {}s",
+            timeout.as_secs_f64()
+        )),
+    };
+    BackendStatus {
+        name: backend.name().to_string(),
+        ready: error.is_none(),
+        latency_ms,
+        error,
+    }
+}
+
+/// Answers `404 Not Found` for files still being written by the filesystem backend.
+async fn hide_partial_files(request: Request, next: Next) -> Response {
+    let path = urlencoding::decode(request.uri().path());
+    if path.map_or(true, |path| path.ends_with(PARTIAL_SUFFIX)) {
+        return ProviderBackendError::NotFound.into_response();
+    }
+    next.run(request).await
+}
+
 /// Build the application router with all routes
-#[allow(clippy::needless_pass_by_value)]
-pub fn app(providers: Arc<dyn Backend>) -> Router {
-    Router::new()
+pub fn app(state: AppState) -> Router {
+    let admin = Router::new()
+        .route(
+            "/admin/providers/{namespace}/{type}/versions/{version}",
+            put(publish::publish_handler).post(publish::publish_handler),
+        )
+        .layer(DefaultBodyLimit::max(state.publish.max_upload_bytes))
+        .route(
+            "/admin/providers/{namespace}/{type}/versions/{version}/status",
+            get(overlay::get_status_handler)
+                .put(overlay::put_status_handler)
+                .delete(overlay::delete_status_handler),
+        )
+        .route(
+            "/admin/providers/{namespace}/{type}/diagnostics",
+            get(diagnose_provider),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            state.auth.clone(),
+            auth::require_token,
+        ));
+
+    let mut router = Router::new()
         .route("/.well-known/terraform.json", get(service_discovery))
         .route(
             "/v1/providers/{namespace}/{type}/versions",
             get(list_versions),
         )
-        .with_state(providers.clone())
         .route(
             "/v1/providers/{namespace}/{type}/{version}/download/{os}/{arch}",
             get(find_provider_package),
         )
+        .route("/v1/search", get(search::search_handler))
+        .route(
+            "/discovery/namespaces",
+            get(discovery::list_namespaces_handler),
+        )
+        .route(
+            "/discovery/namespaces/{namespace}/providers",
+            get(discovery::list_providers_handler),
+        )
+        .route(
+            "/discovery/providers/{namespace}/{type}",
+            get(discovery::provider_metadata_handler),
+        )
+        .route("/index/providers", get(list_indexed_providers))
         .route("/health", get(health_check))
-        .with_state(providers.clone())
-        .layer(TraceLayer::new_for_http())
+        .route("/ready", get(readiness_check))
+        .route("/metrics", get(metrics::metrics_handler))
+        .merge(admin);
+    if let Some(root) = &state.files {
+        router = router.nest(
+            "/files",
+            Router::new()
+                .fallback_service(ServeDir::new(root))
+                .layer(middleware::from_fn(hide_partial_files)),
+        );
+    }
+
+    router
+        .route_layer(middleware::from_fn_with_state(
+            state.metrics.clone(),
+            metrics::track_requests,
+        ))
+        .with_state(state)
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(telemetry::make_request_span)
+                .on_response(DefaultOnResponse::new().level(Level::INFO)),
+        )
+        .layer(PropagateRequestIdLayer::x_request_id())
+        .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::FilesystemConfig;
+    use crate::providers::{FakeBackend, FilesystemStore, StoreBackend};
+    use crate::publish::{SIGNING_KEY_FIELD, Upload};
+    use axum::body::Body;
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn health_check_reports_draining() {
+        let state = AppState::new(Arc::new(FakeBackend), Metrics::new().unwrap());
+        state.shutdown.begin_draining();
+
+        let response = app(state)
+            .oneshot(
+                Request::builder()
+                    .uri("/health")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn versions_honour_if_none_match() {
+        let app = app(
+            AppState::new(Arc::new(FakeBackend), Metrics::new().unwrap()).with_http_cache(
+                HttpCacheConfig {
+                    versions: "public, max-age=300".to_string(),
+                    ..HttpCacheConfig::default()
+                },
+            ),
+        );
+        let uri = "/v1/providers/hashicorp/aws/versions";
+
+        let first = app
+            .clone()
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let etag = first.headers().get(header::ETAG).unwrap().clone();
+        assert_eq!(
+            first.headers().get(header::CACHE_CONTROL).unwrap(),
+            "public, max-age=300"
+        );
+
+        let second = app
+            .oneshot(
+                Request::builder()
+                    .uri(uri)
+                    .header(header::IF_NONE_MATCH, etag)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(second.status(), StatusCode::NOT_MODIFIED);
+    }
+
+    const BOUNDARY: &str = "registry-test-boundary";
+
+    fn multipart_body(upload: Upload) -> Vec<u8> {
+        let mut body = Vec::new();
+        let mut part = |disposition: String, contents: &[u8]| {
+            body.extend(format!("--{BOUNDARY}\r\n{disposition}\r\n\r\n").as_bytes());
+            body.extend(contents);
+            body.extend(b"\r\n");
+        };
+        for file in upload.files {
+            part(
+                format!(
+                    "Content-Disposition: form-data; name=\"file\"; filename=\"{}\"",
+                    file.filename
+                ),
+                &file.contents,
+            );
+        }
+        if let Some(key) = upload.signing_key {
+            part(
+                format!("Content-Disposition: form-data; name=\"{SIGNING_KEY_FIELD}\""),
+                key.as_bytes(),
+            );
+        }
+        body.extend(format!("--{BOUNDARY}--\r\n").as_bytes());
+        body
+    }
+
+    fn upload_request(token: Option<&str>, body: Vec<u8>) -> Request<Body> {
+        let mut request = Request::builder()
+            .method("PUT")
+            .uri("/admin/providers/acme/foo/versions/1.0.0")
+            .header(
+                header::CONTENT_TYPE,
+                format!("multipart/form-data; boundary={BOUNDARY}"),
+            );
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {token}"));
+        }
+        request.body(Body::from(body)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn published_providers_are_served() {
+        let root = tempfile::tempdir().unwrap();
+        let filesystem = FilesystemConfig {
+            root: root.path().to_path_buf(),
+            base_url: "https://registry.example.com/files".to_string(),
+        };
+        let backend = Arc::new(StoreBackend::new(FilesystemStore::new(filesystem.clone())));
+        let app = app(AppState::new(backend.clone(), Metrics::new().unwrap())
+            .with_auth(AuthConfig {
+                tokens: vec!["secret".to_string()],
+            })
+            .with_publisher(backend, PublishConfig::default())
+            .with_files(filesystem.root));
+        let key = publish::tests::secret_key();
+
+        let published = app
+            .clone()
+            .oneshot(upload_request(
+                Some("secret"),
+                multipart_body(publish::tests::signed_upload(&key)),
+            ))
+            .await
+            .unwrap();
+        let republished = app
+            .clone()
+            .oneshot(upload_request(
+                Some("secret"),
+                multipart_body(publish::tests::signed_upload(&key)),
+            ))
+            .await
+            .unwrap();
+        let download = app
+            .oneshot(
+                Request::builder()
+                    .uri("/files/acme/foo/1.0.0/terraform-provider-foo_1.0.0_linux_amd64.zip")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(published.status(), StatusCode::CREATED);
+        assert_eq!(republished.status(), StatusCode::CONFLICT);
+        assert_eq!(download.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn partial_files_are_not_served() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::write(root.path().join("file.zip"), b"done").unwrap();
+        std::fs::write(root.path().join("file.zip.partial"), b"half").unwrap();
+        let app = app(
+            AppState::new(Arc::new(FakeBackend), Metrics::new().unwrap())
+                .with_files(root.path().to_path_buf()),
+        );
+
+        let mut statuses = Vec::new();
+        for uri in [
+            "/files/file.zip",
+            "/files/file.zip.partial",
+            "/files/file.zip%2Epartial",
+        ] {
+            let response = app
+                .clone()
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            statuses.push(response.status());
+        }
+
+        assert_eq!(
+            statuses,
+            vec![StatusCode::OK, StatusCode::NOT_FOUND, StatusCode::NOT_FOUND]
+        );
+    }
+
+    #[tokio::test]
+    async fn published_versions_are_listed_at_once() {
+        let root = tempfile::tempdir().unwrap();
+        let store = Arc::new(StoreBackend::new(FilesystemStore::new(FilesystemConfig {
+            root: root.path().to_path_buf(),
+            base_url: "https://registry.example.com/files".to_string(),
+        })));
+        let index = Arc::new(MetadataIndex::in_memory().unwrap());
+        index.replace_provider("acme", "foo", &[], &[]).unwrap();
+        let metrics = Metrics::new().unwrap();
+        let backend = Arc::new(crate::providers::CachingBackend::new(
+            Arc::new(crate::providers::IndexedBackend::new(store.clone(), index)),
+            &crate::config::CacheConfig {
+                ttl_secs: 300,
+                negative_ttl_secs: 300,
+                max_entries: 100,
+                stale_while_revalidate_secs: 0,
+                stale_if_error_secs: 0,
+            },
+            metrics.clone(),
+        ));
+        let app = app(AppState::new(backend, metrics)
+            .with_auth(AuthConfig {
+                tokens: vec!["secret".to_string()],
+            })
+            .with_publisher(store, PublishConfig::default()));
+        let list = || {
+            app.clone().oneshot(
+                Request::builder()
+                    .uri("/v1/providers/acme/foo/versions")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+        };
+
+        let before = list().await.unwrap();
+        let before = axum::body::to_bytes(before.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let published = app
+            .clone()
+            .oneshot(upload_request(
+                Some("secret"),
+                multipart_body(publish::tests::signed_upload(&publish::tests::secret_key())),
+            ))
+            .await
+            .unwrap();
+        let after = list().await.unwrap();
+        let after = axum::body::to_bytes(after.into_body(), usize::MAX)
+            .await
+            .unwrap();
+
+        let versions = |body: &[u8]| {
+            serde_json::from_slice::<VersionsResponse>(body)
+                .unwrap()
+                .versions
+                .into_iter()
+                .map(|version| version.version)
+                .collect::<Vec<_>>()
+        };
+        assert!(versions(&before).is_empty());
+        assert_eq!(published.status(), StatusCode::CREATED);
+        assert_eq!(versions(&after), vec!["1.0.0"]);
+    }
+
+    #[tokio::test]
+    async fn published_providers_are_discoverable() {
+        let root = tempfile::tempdir().unwrap();
+        let backend = Arc::new(StoreBackend::new(FilesystemStore::new(FilesystemConfig {
+            root: root.path().to_path_buf(),
+            base_url: "https://registry.example.com/files".to_string(),
+        })));
+        let app = app(AppState::new(backend.clone(), Metrics::new().unwrap())
+            .with_auth(AuthConfig {
+                tokens: vec!["secret".to_string()],
+            })
+            .with_publisher(backend, PublishConfig::default()));
+        let key = publish::tests::secret_key();
+        app.clone()
+            .oneshot(upload_request(
+                Some("secret"),
+                multipart_body(publish::tests::signed_upload(&key)),
+            ))
+            .await
+            .unwrap();
+        let get = |uri: &str| {
+            app.clone()
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        };
+
+        let namespaces = get("/discovery/namespaces").await.unwrap();
+        let providers = get("/discovery/namespaces/acme/providers").await.unwrap();
+        let empty = get("/discovery/namespaces/other/providers").await.unwrap();
+        let metadata = get("/discovery/providers/acme/foo").await.unwrap();
+
+        let body = |response: axum::response::Response| async {
+            let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            serde_json::from_slice::<serde_json::Value>(&bytes).unwrap()
+        };
+        assert_eq!(
+            body(namespaces).await,
+            serde_json::json!({ "namespaces": ["acme"] })
+        );
+        assert_eq!(
+            body(providers).await,
+            serde_json::json!({ "namespace": "acme", "providers": ["foo"] })
+        );
+        assert_eq!(empty.status(), StatusCode::NOT_FOUND);
+        let metadata = body(metadata).await;
+        assert_eq!(metadata["latest_version"], "1.0.0");
+        assert!(metadata["published_at"].is_string());
+        assert!(
+            metadata["platforms"]
+                .as_array()
+                .unwrap()
+                .contains(&serde_json::json!({ "os": "linux", "arch": "amd64" }))
+        );
+    }
+
+    #[tokio::test]
+    async fn published_providers_are_searchable() {
+        let root = tempfile::tempdir().unwrap();
+        let backend = Arc::new(StoreBackend::new(FilesystemStore::new(FilesystemConfig {
+            root: root.path().to_path_buf(),
+            base_url: "https://registry.example.com/files".to_string(),
+        })));
+        let app = app(AppState::new(backend.clone(), Metrics::new().unwrap())
+            .with_auth(AuthConfig {
+                tokens: vec!["secret".to_string()],
+            })
+            .with_publisher(backend, PublishConfig::default()));
+        let key = publish::tests::secret_key();
+        app.clone()
+            .oneshot(upload_request(
+                Some("secret"),
+                multipart_body(publish::tests::signed_upload(&key)),
+            ))
+            .await
+            .unwrap();
+        let get = |uri: &str| {
+            app.clone()
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        };
+
+        let found = get("/v1/search?q=foo").await.unwrap();
+        let missed = get("/v1/search?q=bar").await.unwrap();
+        let blank = get("/v1/search?q=%20").await.unwrap();
+
+        let body = |response: axum::response::Response| async {
+            let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            serde_json::from_slice::<serde_json::Value>(&bytes).unwrap()
+        };
+        assert_eq!(found.status(), StatusCode::OK);
+        let found = body(found).await;
+        assert_eq!(found["providers"].as_array().unwrap().len(), 1);
+        assert_eq!(found["providers"][0]["namespace"], "acme");
+        assert_eq!(found["providers"][0]["type"], "foo");
+        assert_eq!(body(missed).await["providers"], serde_json::json!([]));
+        assert_eq!(blank.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            body(blank).await["errors"][0],
+            "the `q` parameter must not be empty"
+        );
+    }
+
+    #[tokio::test]
+    async fn uploads_require_a_token() {
+        let backend = Arc::new(StoreBackend::new(FilesystemStore::new(FilesystemConfig {
+            root: tempfile::tempdir().unwrap().path().to_path_buf(),
+            base_url: "https://registry.example.com/files".to_string(),
+        })));
+        let app = app(AppState::new(backend.clone(), Metrics::new().unwrap())
+            .with_auth(AuthConfig {
+                tokens: vec!["secret".to_string()],
+            })
+            .with_publisher(backend, PublishConfig::default()));
+
+        let missing = app
+            .clone()
+            .oneshot(upload_request(None, Vec::new()))
+            .await
+            .unwrap();
+        let wrong = app
+            .oneshot(upload_request(Some("guess"), Vec::new()))
+            .await
+            .unwrap();
+
+        assert_eq!(missing.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(wrong.status(), StatusCode::UNAUTHORIZED);
+        let body = axum::body::to_bytes(wrong.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["errors"][0], "a valid bearer token is required");
+    }
+
+    #[tokio::test]
+    async fn admin_path_parameters_are_validated() {
+        let backend = Arc::new(StoreBackend::new(FilesystemStore::new(FilesystemConfig {
+            root: tempfile::tempdir().unwrap().path().to_path_buf(),
+            base_url: "https://registry.example.com/files".to_string(),
+        })));
+        let app = app(AppState::new(backend.clone(), Metrics::new().unwrap())
+            .with_auth(AuthConfig {
+                tokens: vec!["secret".to_string()],
+            })
+            .with_publisher(backend, PublishConfig::default()));
+        let mut upload = upload_request(Some("secret"), Vec::new());
+        *upload.uri_mut() = "/admin/providers/acme/foo/versions/v1.0.0".parse().unwrap();
+
+        let upload = app.clone().oneshot(upload).await.unwrap();
+        let status = app.oneshot(status_request("GET", "1.0", "")).await.unwrap();
+
+        assert_eq!(upload.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(status.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn invalid_versions_get_an_errors_body() {
+        let backend = Arc::new(StoreBackend::new(FilesystemStore::new(FilesystemConfig {
+            root: tempfile::tempdir().unwrap().path().to_path_buf(),
+            base_url: "https://registry.example.com/files".to_string(),
+        })));
+        let app = app(AppState::new(backend.clone(), Metrics::new().unwrap())
+            .with_auth(AuthConfig {
+                tokens: vec!["secret".to_string()],
+            })
+            .with_publisher(backend, PublishConfig::default()));
+        let mut upload = upload_request(Some("secret"), Vec::new());
+        *upload.uri_mut() = "/admin/providers/acme/foo/versions/v1.0.0".parse().unwrap();
+        let download = Request::builder()
+            .uri("/v1/providers/acme/foo/v1.0.0/download/linux/amd64")
+            .body(Body::empty())
+            .unwrap();
+
+        for request in [upload, status_request("GET", "v1.0.0", ""), download] {
+            let response = app.clone().oneshot(request).await.unwrap();
+
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert!(
+                body["errors"][0]
+                    .as_str()
+                    .unwrap()
+                    .contains("`v1.0.0` is not a valid version"),
+                "{body}"
+            );
+        }
+    }
+
+    fn status_request(method: &str, version: &str, body: &str) -> Request<Body> {
+        Request::builder()
+            .method(method)
+            .uri(format!(
+                "/admin/providers/hashicorp/aws/versions/{version}/status"
+            ))
+            .header(header::AUTHORIZATION, "Bearer secret")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn version_statuses_apply_to_listings() {
+        let directory = tempfile::tempdir().unwrap();
+        let overlay = Arc::new(
+            VersionOverlay::open(&crate::overlay::OverlayConfig {
+                path: directory.path().join("overlay.json"),
+            })
+            .unwrap(),
+        );
+        let backend = Arc::new(crate::providers::OverlayBackend::new(
+            Arc::new(FakeBackend),
+            overlay.clone(),
+        ));
+        let app = app(AppState::new(backend, Metrics::new().unwrap())
+            .with_auth(AuthConfig {
+                tokens: vec!["secret".to_string()],
+            })
+            .with_overlay(overlay));
+
+        let yanked = app
+            .clone()
+            .oneshot(status_request("PUT", "1.0.0", r#"{"status":"yanked"}"#))
+            .await
+            .unwrap();
+        let deprecated = app
+            .clone()
+            .oneshot(status_request(
+                "PUT",
+                "0.9.0",
+                r#"{"status":"deprecated","message":"upgrade to 1.x"}"#,
+            ))
+            .await
+            .unwrap();
+        let listing = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/v1/providers/hashicorp/aws/versions")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let download = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/v1/providers/hashicorp/aws/1.0.0/download/linux/amd64")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let cleared = app
+            .clone()
+            .oneshot(status_request("DELETE", "1.0.0", ""))
+            .await
+            .unwrap();
+        let status = app
+            .oneshot(status_request("GET", "1.0.0", ""))
+            .await
+            .unwrap();
+
+        assert_eq!(yanked.status(), StatusCode::OK);
+        assert_eq!(deprecated.status(), StatusCode::OK);
+        assert_eq!(download.status(), StatusCode::OK);
+        assert_eq!(cleared.status(), StatusCode::NO_CONTENT);
+        assert_eq!(status.status(), StatusCode::NOT_FOUND);
+        let body = axum::body::to_bytes(status.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["errors"][0], "not found");
+        let body = axum::body::to_bytes(listing.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let listing: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(listing["versions"].as_array().unwrap().len(), 1);
+        assert_eq!(
+            listing["warnings"][0],
+            "hashicorp/aws 0.9.0 is deprecated: upgrade to 1.x"
+        );
+    }
+
+    #[tokio::test]
+    async fn versions_are_listed_in_semver_order() {
+        let response = app(AppState::new(
+            Arc::new(FakeBackend),
+            Metrics::new().unwrap(),
+        ))
+        .oneshot(
+            Request::builder()
+                .uri("/v1/providers/hashicorp/aws/versions")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let listing: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let versions: Vec<_> = listing["versions"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|version| version["version"].as_str().unwrap())
+            .collect();
+        assert_eq!(versions, vec!["0.9.0", "1.0.0"]);
+    }
+
+    #[tokio::test]
+    async fn versions_can_be_filtered_by_constraint() {
+        let app = app(AppState::new(
+            Arc::new(FakeBackend),
+            Metrics::new().unwrap(),
+        ));
+        let request = |query: &str| {
+            Request::builder()
+                .uri(format!("/v1/providers/hashicorp/aws/versions?{query}"))
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let matching = app
+            .clone()
+            .oneshot(request("constraint=~%3E0.9,!=1.0.0"))
+            .await
+            .unwrap();
+        let latest = app
+            .clone()
+            .oneshot(request("constraint=latest"))
+            .await
+            .unwrap();
+        let invalid = app.oneshot(request("constraint=~%3E1.x")).await.unwrap();
+
+        assert_eq!(invalid.status(), StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(matching.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let matching: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(matching["versions"].as_array().unwrap().len(), 1);
+        assert_eq!(matching["versions"][0]["version"], "0.9.0");
+        assert_eq!(matching["latest"], "0.9.0");
+        let body = axum::body::to_bytes(latest.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let latest: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(latest["versions"].as_array().unwrap().len(), 1);
+        assert_eq!(latest["latest"], "1.0.0");
+    }
+
+    #[tokio::test]
+    async fn malformed_path_parameters_are_rejected() {
+        let app = app(AppState::new(
+            Arc::new(FakeBackend),
+            Metrics::new().unwrap(),
+        ));
+
+        let mut statuses = Vec::new();
+        for uri in [
+            "/v1/providers/hashi_corp/aws/versions",
+            "/v1/providers/hashicorp/aws/v1.0/download/linux/amd64",
+            "/v1/providers/hashicorp/aws/1.0.0/download/Linux/amd64",
+        ] {
+            let response = app
+                .clone()
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            statuses.push(response.status());
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert!(
+                body["errors"][0]
+                    .as_str()
+                    .unwrap()
+                    .contains("is not a valid"),
+                "{body}"
+            );
+        }
+
+        assert_eq!(statuses, vec![StatusCode::BAD_REQUEST; 3]);
+    }
+
+    #[tokio::test]
+    async fn diagnostics_require_a_token() {
+        let app = app(
+            AppState::new(Arc::new(FakeBackend), Metrics::new().unwrap()).with_auth(AuthConfig {
+                tokens: vec!["secret".to_string()],
+            }),
+        );
+        let request = |token: Option<&str>| {
+            let mut request = Request::builder().uri("/admin/providers/hashicorp/aws/diagnostics");
+            if let Some(token) = token {
+                request = request.header(header::AUTHORIZATION, format!("Bearer {token}"));
+            }
+            request.body(Body::empty()).unwrap()
+        };
+
+        let anonymous = app.clone().oneshot(request(None)).await.unwrap();
+        let authorized = app.oneshot(request(Some("secret"))).await.unwrap();
+
+        assert_eq!(anonymous.status(), StatusCode::UNAUTHORIZED);
+        // The fake backend serves exactly what it holds, so it has nothing to diagnose.
+        assert_eq!(authorized.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn indexed_providers_requires_index() {
+        let response = app(AppState::new(
+            Arc::new(FakeBackend),
+            Metrics::new().unwrap(),
+        ))
+        .oneshot(
+            Request::builder()
+                .uri("/index/providers")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn stale_responses_carry_age_and_warning() {
+        let mut headers = HeaderMap::new();
+        set_freshness_headers(
+            &mut headers,
+            Freshness {
+                age: std::time::Duration::from_secs(90),
+                staleness: Staleness::RevalidationFailed,
+            },
+        );
+
+        assert_eq!(headers.get(header::AGE).unwrap(), "90");
+        assert_eq!(
+            headers.get(header::WARNING).unwrap(),
+            r#"111 - "Revalidation Failed""#
+        );
+    }
+
+    #[tokio::test]
+    async fn request_id_is_generated_when_missing() {
+        let response = app(AppState::new(
+            Arc::new(FakeBackend),
+            Metrics::new().unwrap(),
+        ))
+        .oneshot(
+            Request::builder()
+                .uri("/health")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+        let request_id = response
+            .headers()
+            .get(telemetry::REQUEST_ID_HEADER)
+            .unwrap();
+        assert!(!request_id.is_empty());
+    }
+
+    #[tokio::test]
+    async fn request_id_is_propagated_to_error_responses() {
+        let response = app(AppState::new(
+            Arc::new(FakeBackend),
+            Metrics::new().unwrap(),
+        ))
+        .oneshot(
+            Request::builder()
+                .uri("/v1/providers/hashicorp")
+                .header(telemetry::REQUEST_ID_HEADER, "abc-123")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            response
+                .headers()
+                .get(telemetry::REQUEST_ID_HEADER)
+                .unwrap(),
+            "abc-123"
+        );
+    }
+
+    #[tokio::test]
+    async fn readiness_check_reports_backend_status() {
+        let response = app(AppState::new(
+            Arc::new(FakeBackend),
+            Metrics::new().unwrap(),
+        ))
+        .oneshot(
+            Request::builder()
+                .uri("/ready")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let readiness: ReadinessResponse = serde_json::from_slice(&body).unwrap();
+        assert!(readiness.ready);
+        assert_eq!(readiness.backends.len(), 1);
+        assert_eq!(readiness.backends[0].name, "fake");
+        assert!(readiness.backends[0].ready);
+    }
+
+    struct HangingBackend;
+
+    impl Backend for HangingBackend {
+        fn name(&self) -> &'static str {
+            "hanging"
+        }
+
+        fn health(&self) -> crate::providers::Result<()> {
+            std::thread::sleep(Duration::from_millis(200));
+            Ok(())
+        }
+
+        fn list_provider_versions(
+            &self,
+            _: Namespace,
+            _: ProviderType,
+        ) -> crate::providers::Result<Vec<crate::types::VersionInfo>> {
+            Err(ProviderBackendError::NotFound)
+        }
+
+        fn find_provider_package(
+            &self,
+            _: Namespace,
+            _: ProviderType,
+            _: Version,
+            _: Os,
+            _: Arch,
+        ) -> crate::providers::Result<crate::types::Package> {
+            Err(ProviderBackendError::NotFound)
+        }
+    }
+
+    #[tokio::test]
+    async fn readiness_probe_times_out() {
+        let status = probe_backend(Arc::new(HangingBackend), Duration::from_millis(10)).await;
+
+        assert!(!status.ready);
+        assert_eq!(
+            status.error.as_deref(),
+            Some("health check timed out after 0.01s")
+        );
+    }
+
+    #[tokio::test]
+    async fn metrics_endpoint_reports_requests_and_downloads() {
+        let app = app(AppState::new(
+            Arc::new(FakeBackend),
+            Metrics::new().unwrap(),
+        ));
+
+        app.clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/v1/providers/hashicorp/aws/1.0.0/download/linux/amd64")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/metrics")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.contains(
+            r#"route="/v1/providers/{namespace}/{type}/{version}/download/{os}/{arch}",status="200"} 1"#
+        ));
+        assert!(body.contains(
+            r#"terraform_registry_downloads_total{arch="amd64",namespace="hashicorp",os="linux",type="aws",version="1.0.0"} 1"#
+        ));
+    }
 }
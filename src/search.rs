@@ -0,0 +1,217 @@
+use axum::{
+    Json,
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde_derive::Deserialize;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::providers::{Backend, ProviderDetails, Result};
+use crate::routes::call_backend;
+use crate::types::{SearchMeta, SearchResponse, SearchResult};
+
+const DEFAULT_LIMIT: usize = 15;
+const MAX_LIMIT: usize = 100;
+
+/// Query parameters of `/v1/search`
+#[derive(Debug, Deserialize)]
+pub struct SearchQuery {
+    /// Words that must each appear in a provider's name, namespace, description or tags.
+    q: Option<String>,
+    #[serde(default)]
+    offset: usize,
+    limit: Option<usize>,
+}
+
+/// Find providers across every namespace the backend can enumerate, a page at a time
+pub async fn search_handler(
+    State(backend): State<Arc<dyn Backend>>,
+    Query(query): Query<SearchQuery>,
+) -> Response {
+    let terms = terms(query.q.as_deref().unwrap_or_default());
+    if terms.is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "errors": ["the `q` parameter must not be empty"] })),
+        )
+            .into_response();
+    }
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    info!(?terms, offset = query.offset, limit, "Search requested");
+
+    match call_backend(backend, catalog).await {
+        Ok(catalog) => Json(search(catalog, &terms, query.offset, limit)).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+fn terms(query: &str) -> Vec<String> {
+    query.split_whitespace().map(str::to_lowercase).collect()
+}
+
+/// Every provider the backend lists, with its description and tags. A provider that cannot be
+/// described is still found by name. With a cache configured, neither call reaches the upstream
+/// again until the entries expire.
+fn catalog(backend: &dyn Backend) -> Result<Vec<SearchResult>> {
+    Ok(backend
+        .list_providers()?
+        .into_iter()
+        .map(|provider| {
+            let details = backend
+                .describe_provider(provider.namespace.clone(), provider.provider_type.clone())
+                .unwrap_or_else(|error| {
+                    warn!(
+                        namespace = %provider.namespace,
+                        provider_type = %provider.provider_type,
+                        %error,
+                        "Cannot describe provider for search"
+                    );
+                    ProviderDetails::default()
+                });
+            SearchResult {
+                namespace: provider.namespace.into(),
+                provider_type: provider.provider_type.into(),
+                description: details.description,
+                tags: details.tags,
+            }
+        })
+        .collect())
+}
+
+/// How well a provider matches, or `None` when some term appears nowhere. Terms found in the
+/// provider's name count for more than those found in its namespace, then tags, then description.
+fn score(result: &SearchResult, terms: &[String]) -> Option<u32> {
+    let name = result.provider_type.to_lowercase();
+    let namespace = result.namespace.to_lowercase();
+    let description = result
+        .description
+        .as_deref()
+        .unwrap_or_default()
+        .to_lowercase();
+    let tags: Vec<String> = result.tags.iter().map(|tag| tag.to_lowercase()).collect();
+
+    terms
+        .iter()
+        .map(|term| {
+            if name == *term {
+                Some(16)
+            } else if name.contains(term.as_str()) {
+                Some(8)
+            } else if namespace.contains(term.as_str()) {
+                Some(4)
+            } else if tags.iter().any(|tag| tag.contains(term.as_str())) {
+                Some(2)
+            } else if description.contains(term.as_str()) {
+                Some(1)
+            } else {
+                None
+            }
+        })
+        .sum()
+}
+
+/// Ranks the matching providers, ties broken by namespace and name, and cuts out one page.
+fn search(
+    catalog: Vec<SearchResult>,
+    terms: &[String],
+    offset: usize,
+    limit: usize,
+) -> SearchResponse {
+    let mut matches: Vec<(u32, SearchResult)> = catalog
+        .into_iter()
+        .filter_map(|result| Some((score(&result, terms)?, result)))
+        .collect();
+    matches.sort_by(|(a_score, a), (b_score, b)| {
+        b_score
+            .cmp(a_score)
+            .then_with(|| a.namespace.cmp(&b.namespace))
+            .then_with(|| a.provider_type.cmp(&b.provider_type))
+    });
+    let total = matches.len();
+
+    SearchResponse {
+        meta: SearchMeta {
+            limit,
+            current_offset: offset,
+            next_offset: (offset.saturating_add(limit) < total).then(|| offset + limit),
+            prev_offset: (offset > 0).then(|| offset.saturating_sub(limit)),
+        },
+        providers: matches
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .map(|(_, result)| result)
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider(namespace: &str, name: &str, description: &str, tags: &[&str]) -> SearchResult {
+        SearchResult {
+            namespace: namespace.to_string(),
+            provider_type: name.to_string(),
+            description: Some(description.to_string()),
+            tags: tags.iter().map(|tag| (*tag).to_string()).collect(),
+        }
+    }
+
+    fn catalog() -> Vec<SearchResult> {
+        vec![
+            provider("acme", "dns", "Manages DNS zones", &["networking"]),
+            provider("acme", "vault", "Reads secrets", &["security"]),
+            provider("infra", "cloudflare", "DNS and CDN settings", &[]),
+            provider("netops", "firewall", "Edge firewall rules", &["Networking"]),
+        ]
+    }
+
+    fn names(response: &SearchResponse) -> Vec<&str> {
+        response
+            .providers
+            .iter()
+            .map(|result| result.provider_type.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn names_rank_above_descriptions_and_tags() {
+        let dns = search(catalog(), &terms("DNS"), 0, 10);
+        let networking = search(catalog(), &terms("networking"), 0, 10);
+
+        assert_eq!(names(&dns), vec!["dns", "cloudflare"]);
+        assert_eq!(names(&networking), vec!["dns", "firewall"]);
+    }
+
+    #[test]
+    fn every_term_must_match() {
+        let found = search(catalog(), &terms("acme secrets"), 0, 10);
+        let missing = search(catalog(), &terms("acme kubernetes"), 0, 10);
+
+        assert_eq!(names(&found), vec!["vault"]);
+        assert!(missing.providers.is_empty());
+    }
+
+    #[test]
+    fn results_are_paginated() {
+        let first = search(catalog(), &terms("e"), 0, 2);
+        let second = search(catalog(), &terms("e"), 2, 2);
+
+        assert_eq!(first.providers.len(), 2);
+        assert_eq!(
+            first.meta,
+            SearchMeta {
+                limit: 2,
+                current_offset: 0,
+                next_offset: Some(2),
+                prev_offset: None,
+            }
+        );
+        assert_eq!(second.providers.len(), 2);
+        assert_eq!(second.meta.next_offset, None);
+        assert_eq!(second.meta.prev_offset, Some(0));
+    }
+}
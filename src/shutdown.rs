@@ -0,0 +1,126 @@
+use serde_derive::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::info;
+
+/// Controls how the server winds down after receiving SIGTERM or SIGINT.
+#[derive(Deserialize, Serialize, PartialEq, Clone, Debug)]
+pub struct ShutdownConfig {
+    /// How long to keep accepting connections while `/health` reports not-ready, giving load
+    /// balancers time to stop routing to this instance.
+    #[serde(default)]
+    pub shutdown_delay_secs: u64,
+    /// Maximum time to wait for in-flight requests before connections are closed.
+    #[serde(default = "default_drain_timeout_secs")]
+    pub drain_timeout_secs: u64,
+}
+
+fn default_drain_timeout_secs() -> u64 {
+    30
+}
+
+impl Default for ShutdownConfig {
+    fn default() -> Self {
+        Self {
+            shutdown_delay_secs: 0,
+            drain_timeout_secs: default_drain_timeout_secs(),
+        }
+    }
+}
+
+impl ShutdownConfig {
+    pub fn shutdown_delay(&self) -> Duration {
+        Duration::from_secs(self.shutdown_delay_secs)
+    }
+
+    pub fn drain_timeout(&self) -> Duration {
+        Duration::from_secs(self.drain_timeout_secs)
+    }
+}
+
+/// Shared draining flag, flipped once a shutdown signal arrives.
+#[derive(Clone)]
+pub struct Shutdown {
+    draining: Arc<watch::Sender<bool>>,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self {
+            draining: Arc::new(watch::Sender::new(false)),
+        }
+    }
+}
+
+impl Shutdown {
+    pub fn is_draining(&self) -> bool {
+        *self.draining.borrow()
+    }
+
+    pub fn begin_draining(&self) {
+        self.draining.send_replace(true);
+    }
+
+    /// Resolves once draining has begun.
+    pub async fn draining(&self) {
+        let mut receiver = self.draining.subscribe();
+        // The sender lives as long as `self`, so this cannot fail.
+        let _ = receiver.wait_for(|draining| *draining).await;
+    }
+
+    /// Waits for SIGTERM or SIGINT, marks the instance as draining, then waits out the configured
+    /// shutdown delay before letting the server stop accepting connections.
+    pub async fn wait_for_signal(self, config: ShutdownConfig) {
+        signal().await;
+        info!("Shutdown signal received, draining connections");
+        self.begin_draining();
+        tokio::time::sleep(config.shutdown_delay()).await;
+    }
+}
+
+async fn signal() {
+    let ctrl_c = async {
+        if tokio::signal::ctrl_c().await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(_) => std::future::pending::<()>().await,
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        () = ctrl_c => {},
+        () = terminate => {},
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn draining_resolves_after_begin_draining() {
+        let shutdown = Shutdown::default();
+        assert!(!shutdown.is_draining());
+
+        let waiter = tokio::spawn({
+            let shutdown = shutdown.clone();
+            async move { shutdown.draining().await }
+        });
+        shutdown.begin_draining();
+
+        waiter.await.unwrap();
+        assert!(shutdown.is_draining());
+    }
+}
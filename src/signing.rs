@@ -0,0 +1,270 @@
+use pgp::composed::{ArmorOptions, Deserializable, DetachedSignature, SignedSecretKey};
+use pgp::crypto::hash::HashAlgorithm;
+use pgp::ser::Serialize as _;
+use pgp::types::{KeyDetails, Password};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::path::PathBuf;
+use thiserror::Error;
+
+use crate::publish::{PublishError, ReleaseFile, SIGNING_KEY_FIELD, Upload, sha256_hex};
+use crate::types::GpgPublicKey;
+
+/// Lets the registry sign releases on behalf of publishers in the listed namespaces, who then
+/// upload only their packages.
+#[derive(Deserialize, Serialize, PartialEq, Clone, Debug)]
+pub struct SigningConfig {
+    /// ASCII-armored PGP secret key whose primary key can sign.
+    pub key_path: PathBuf,
+    #[serde(default)]
+    pub passphrase: Option<String>,
+    pub namespaces: Vec<String>,
+}
+
+#[derive(Error, Debug)]
+pub enum SigningError {
+    #[error("cannot read signing key: {0}")]
+    Read(#[from] std::io::Error),
+    #[error("invalid signing key: {0}")]
+    Key(#[from] pgp::errors::Error),
+}
+
+/// Manifest written for releases uploaded without one.
+const DEFAULT_MANIFEST: &[u8] = br#"{"version":1,"metadata":{"protocol_versions":["5.0"]}}"#;
+
+pub struct RegistrySigner {
+    key: SignedSecretKey,
+    passphrase: String,
+    public_key: GpgPublicKey,
+    namespaces: HashSet<String>,
+}
+
+impl RegistrySigner {
+    /// Loads the key and signs a test message, so a wrong passphrase fails at startup rather than
+    /// on the first upload.
+    pub fn load(config: &SigningConfig) -> Result<Self, SigningError> {
+        let armored = std::fs::read_to_string(&config.key_path)?;
+        let (key, _) = SignedSecretKey::from_string(&armored)?;
+        let public_key = GpgPublicKey {
+            key_id: key.legacy_key_id().to_string().to_ascii_uppercase(),
+            ascii_armor: key
+                .to_public_key()
+                .to_armored_string(ArmorOptions::default())?,
+        };
+
+        let signer = Self {
+            key,
+            passphrase: config.passphrase.clone().unwrap_or_default(),
+            public_key,
+            namespaces: config.namespaces.iter().cloned().collect(),
+        };
+        signer.sign(b"")?;
+        Ok(signer)
+    }
+
+    pub fn signs_for(&self, namespace: &str) -> bool {
+        self.namespaces.contains(namespace)
+    }
+
+    /// Terraform only reads binary signatures, so the signature is not armored.
+    fn sign(&self, data: &[u8]) -> Result<Vec<u8>, pgp::errors::Error> {
+        DetachedSignature::sign_binary_data(
+            rand::thread_rng(),
+            &self.key.primary_key,
+            &Password::from(self.passphrase.as_str()),
+            HashAlgorithm::Sha256,
+            data,
+        )?
+        .to_bytes()
+    }
+
+    /// Adds the manifest (when missing), SHA256SUMS, its signature and the registry's public key
+    /// to an upload of bare packages, leaving naming checks to the usual validation.
+    pub fn complete(&self, prefix: &str, upload: &mut Upload) -> Result<(), PublishError> {
+        let shasums_filename = format!("{prefix}_SHA256SUMS");
+        let signature_filename = format!("{prefix}_SHA256SUMS.sig");
+        let manifest_filename = format!("{prefix}_manifest.json");
+
+        if upload.signing_key.is_some() {
+            return Err(PublishError::SignedByRegistry(
+                SIGNING_KEY_FIELD.to_string(),
+            ));
+        }
+        if let Some(file) = upload
+            .files
+            .iter()
+            .find(|file| file.filename == shasums_filename || file.filename == signature_filename)
+        {
+            return Err(PublishError::SignedByRegistry(file.filename.clone()));
+        }
+        if !upload
+            .files
+            .iter()
+            .any(|file| file.filename == manifest_filename)
+        {
+            upload.files.push(ReleaseFile {
+                filename: manifest_filename,
+                contents: DEFAULT_MANIFEST.to_vec(),
+            });
+        }
+
+        let mut files: Vec<_> = upload.files.iter().collect();
+        files.sort_by(|a, b| a.filename.cmp(&b.filename));
+        let shasums = files.iter().fold(String::new(), |mut shasums, file| {
+            let _ = writeln!(shasums, "{}  {}", sha256_hex(&file.contents), file.filename);
+            shasums
+        });
+        let signature = self
+            .sign(shasums.as_bytes())
+            .map_err(PublishError::SigningFailed)?;
+
+        upload.files.extend([
+            ReleaseFile {
+                filename: shasums_filename,
+                contents: shasums.into_bytes(),
+            },
+            ReleaseFile {
+                filename: signature_filename,
+                contents: signature,
+            },
+        ]);
+        upload.signing_key = Some(self.public_key.ascii_armor.clone());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::config::{FilesystemConfig, PlatformsConfig};
+    use crate::package::PackageCheckConfig;
+    use crate::package::tests::provider_zip;
+    use crate::providers::{FilesystemStore, StoreBackend, WritableBackend};
+    use crate::publish::ProviderRelease;
+    use crate::publish::tests::secret_key;
+
+    /// A signer for the `acme` namespace, with its key written to a temporary file.
+    pub fn signer() -> RegistrySigner {
+        let directory = tempfile::tempdir().unwrap();
+        let key_path = directory.path().join("registry.asc");
+        std::fs::write(
+            &key_path,
+            secret_key()
+                .to_armored_string(ArmorOptions::default())
+                .unwrap(),
+        )
+        .unwrap();
+
+        RegistrySigner::load(&SigningConfig {
+            key_path,
+            passphrase: None,
+            namespaces: vec!["acme".to_string()],
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn load_rejects_missing_key() {
+        let result = RegistrySigner::load(&SigningConfig {
+            key_path: "/nonexistent/registry.asc".into(),
+            passphrase: None,
+            namespaces: Vec::new(),
+        });
+
+        assert!(matches!(result, Err(SigningError::Read(_))));
+    }
+
+    fn bare_upload() -> Upload {
+        Upload {
+            files: vec![ReleaseFile {
+                filename: "terraform-provider-foo_1.0.0_linux_amd64.zip".to_string(),
+                contents: provider_zip("foo", "1.0.0", "linux", "amd64"),
+            }],
+            signing_key: None,
+        }
+    }
+
+    fn validate(namespace: &str, upload: Upload) -> Result<ProviderRelease, PublishError> {
+        ProviderRelease::validate(
+            namespace.parse().unwrap(),
+            "foo".parse().unwrap(),
+            "1.0.0".parse().unwrap(),
+            upload,
+            Some(&signer()),
+            &PackageCheckConfig::default(),
+            &PlatformsConfig::default(),
+        )
+    }
+
+    #[test]
+    fn signs_bare_packages_with_registry_key() {
+        let signer = signer();
+
+        let release = ProviderRelease::validate(
+            "acme".parse().unwrap(),
+            "foo".parse().unwrap(),
+            "1.0.0".parse().unwrap(),
+            bare_upload(),
+            Some(&signer),
+            &PackageCheckConfig::default(),
+            &PlatformsConfig::default(),
+        )
+        .unwrap();
+
+        assert_eq!(release.signing_key, signer.public_key);
+        assert_eq!(release.protocols, vec!["5.0"]);
+        assert_eq!(
+            String::from_utf8(release.shasums.contents).unwrap(),
+            format!(
+                "{}  terraform-provider-foo_1.0.0_linux_amd64.zip\n\
+                 {}  terraform-provider-foo_1.0.0_manifest.json\n",
+                sha256_hex(&provider_zip("foo", "1.0.0", "linux", "amd64")),
+                sha256_hex(DEFAULT_MANIFEST),
+            )
+        );
+    }
+
+    #[test]
+    fn stores_a_binary_signature() {
+        let root = tempfile::tempdir().unwrap();
+        let store = StoreBackend::new(FilesystemStore::new(FilesystemConfig {
+            root: root.path().to_path_buf(),
+            base_url: "https://registry.example.com/files".to_string(),
+        }));
+        let release = validate("acme", bare_upload()).unwrap();
+
+        store.publish_provider_version(&release).unwrap();
+
+        let stored = std::fs::read(
+            root.path()
+                .join("acme/foo/1.0.0/terraform-provider-foo_1.0.0_SHA256SUMS.sig"),
+        )
+        .unwrap();
+        assert!(!stored.starts_with(b"-----BEGIN"));
+        assert!(DetachedSignature::from_bytes(&stored[..]).is_ok());
+    }
+
+    #[test]
+    fn rejects_publisher_signatures_in_registry_signed_namespaces() {
+        let mut upload = bare_upload();
+        upload.files.push(ReleaseFile {
+            filename: "terraform-provider-foo_1.0.0_SHA256SUMS".to_string(),
+            contents: Vec::new(),
+        });
+
+        assert!(matches!(
+            validate("acme", upload),
+            Err(PublishError::SignedByRegistry(_))
+        ));
+    }
+
+    #[test]
+    fn other_namespaces_still_need_publisher_signatures() {
+        assert!(matches!(
+            validate("hashicorp", bare_upload()),
+            Err(PublishError::MissingFile(filename))
+                if filename == "terraform-provider-foo_1.0.0_SHA256SUMS"
+        ));
+    }
+}
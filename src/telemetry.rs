@@ -0,0 +1,202 @@
+use axum::http::Request;
+use opentelemetry::global;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_http::HeaderExtractor;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::Resource;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use serde_derive::{Deserialize, Serialize};
+use tracing::level_filters::LevelFilter;
+use tracing::{Span, field, warn};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{Layer, Registry, fmt};
+
+/// Logging and trace export settings.
+#[derive(Deserialize, Serialize, PartialEq, Clone, Debug, Default)]
+pub struct TelemetryConfig {
+    #[serde(default)]
+    pub log_format: LogFormat,
+    /// Export spans to an OpenTelemetry collector over OTLP/HTTP when set.
+    pub otlp: Option<OtlpConfig>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Clone, Copy, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    /// Human-readable single-line output.
+    #[default]
+    Compact,
+    /// One JSON object per line, with span fields such as `request_id` flattened in.
+    Json,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Clone, Debug)]
+pub struct OtlpConfig {
+    /// Collector traces endpoint, e.g. `http://otel-collector:4318/v1/traces`.
+    pub endpoint: String,
+    #[serde(default = "default_service_name")]
+    pub service_name: String,
+}
+
+/// Header carrying the request ID, generated when the caller does not send one.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+fn default_service_name() -> String {
+    env!("CARGO_PKG_NAME").to_string()
+}
+
+/// Keeps the tracer provider alive so buffered spans can be flushed on shutdown.
+pub struct Telemetry {
+    provider: Option<SdkTracerProvider>,
+}
+
+impl Telemetry {
+    /// Installs the global tracing subscriber, adding an OTLP exporter when configured.
+    pub fn init(config: &TelemetryConfig) -> anyhow::Result<Self> {
+        global::set_text_map_propagator(TraceContextPropagator::new());
+
+        let fmt_layer: Box<dyn Layer<Registry> + Send + Sync> = match config.log_format {
+            LogFormat::Compact => fmt::layer().with_target(false).compact().boxed(),
+            LogFormat::Json => fmt::layer()
+                .with_target(false)
+                .json()
+                .flatten_event(true)
+                .with_current_span(false)
+                .with_span_list(true)
+                .boxed(),
+        };
+        let fmt_layer = fmt_layer.with_filter(LevelFilter::INFO);
+
+        let provider = config
+            .otlp
+            .as_ref()
+            .map(|otlp| -> anyhow::Result<SdkTracerProvider> {
+                let exporter = SpanExporter::builder()
+                    .with_http()
+                    .with_endpoint(&otlp.endpoint)
+                    .build()?;
+
+                Ok(SdkTracerProvider::builder()
+                    .with_batch_exporter(exporter)
+                    .with_resource(
+                        Resource::builder()
+                            .with_service_name(otlp.service_name.clone())
+                            .build(),
+                    )
+                    .build())
+            })
+            .transpose()?;
+
+        let otel_layer = provider.as_ref().map(|provider| {
+            tracing_opentelemetry::layer()
+                .with_tracer(provider.tracer(env!("CARGO_PKG_NAME")))
+                .with_filter(LevelFilter::INFO)
+        });
+
+        tracing_subscriber::registry()
+            .with(fmt_layer)
+            .with(otel_layer)
+            .try_init()?;
+
+        Ok(Self { provider })
+    }
+
+    /// Flushes any spans still queued for export.
+    pub fn shutdown(self) {
+        if let Some(provider) = self.provider
+            && let Err(error) = provider.shutdown()
+        {
+            warn!(%error, "Failed to flush traces");
+        }
+    }
+}
+
+/// Creates the span for an incoming request, continuing any W3C `traceparent` the caller sent.
+///
+/// The provider fields start empty and are filled in by the handlers, so that every log line
+/// emitted while serving the request, including the access log, carries them.
+pub fn make_request_span<B>(request: &Request<B>) -> Span {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+
+    let span = tracing::info_span!(
+        "request",
+        request_id,
+        method = %request.method(),
+        uri = %request.uri(),
+        http_version = ?request.version(),
+        namespace = field::Empty,
+        provider_type = field::Empty,
+        version = field::Empty,
+        os = field::Empty,
+        arch = field::Empty,
+    );
+
+    let parent = global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HeaderExtractor(request.headers()))
+    });
+    let _ = span.set_parent(parent);
+
+    span
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::Metrics;
+    use crate::providers::{FakeBackend, InstrumentedBackend};
+    use crate::routes::{self, AppState};
+    use axum::body::Body;
+    use opentelemetry_sdk::trace::InMemorySpanExporter;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn backend_spans_continue_incoming_trace() {
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        let _guard = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")))
+            .set_default();
+
+        let metrics = Metrics::new().unwrap();
+        let backend = Arc::new(InstrumentedBackend::new(
+            Arc::new(FakeBackend),
+            metrics.clone(),
+        ));
+        routes::app(AppState::new(backend, metrics))
+            .oneshot(
+                Request::builder()
+                    .uri("/v1/providers/hashicorp/aws/versions")
+                    .header(
+                        "traceparent",
+                        "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+                    )
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        provider.force_flush().unwrap();
+        let spans = exporter.get_finished_spans().unwrap();
+        let backend_span = spans
+            .iter()
+            .find(|span| span.name == "backend_call")
+            .unwrap();
+
+        assert_eq!(
+            backend_span.span_context.trace_id().to_string(),
+            "4bf92f3577b34da6a3ce929d0e0e4736"
+        );
+    }
+}
@@ -0,0 +1,216 @@
+use axum_server::tls_rustls::RustlsConfig;
+use rustls::RootCertStore;
+use rustls::ServerConfig;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::WebPkiClientVerifier;
+use serde_derive::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use thiserror::Error;
+use tracing::{info, warn};
+
+/// TLS settings for terminating HTTPS in the registry itself.
+#[derive(Deserialize, Serialize, PartialEq, Clone, Debug)]
+pub struct TlsConfig {
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+    /// When set, clients must present a certificate signed by this CA (mTLS).
+    pub client_ca_path: Option<PathBuf>,
+    #[serde(default = "default_reload_interval_secs")]
+    pub reload_interval_secs: u64,
+}
+
+fn default_reload_interval_secs() -> u64 {
+    60
+}
+
+#[derive(Error, Debug)]
+pub enum TlsError {
+    #[error("failed to read {0}: {1}")]
+    Io(PathBuf, std::io::Error),
+    #[error("no certificates found in {0}")]
+    NoCertificates(PathBuf),
+    #[error("no private key found in {0}")]
+    NoPrivateKey(PathBuf),
+    #[error("invalid TLS configuration: {0}")]
+    Rustls(#[from] rustls::Error),
+    #[error("invalid client CA: {0}")]
+    ClientVerifier(#[from] rustls::server::VerifierBuilderError),
+}
+
+impl TlsConfig {
+    /// Builds a rustls server configuration from the files on disk.
+    pub fn server_config(&self) -> Result<ServerConfig, TlsError> {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let certs = load_certs(&self.cert_path)?;
+        let key = load_key(&self.key_path)?;
+
+        let builder = ServerConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()?;
+
+        let builder = match &self.client_ca_path {
+            Some(ca_path) => {
+                let mut roots = RootCertStore::empty();
+                for cert in load_certs(ca_path)? {
+                    roots.add(cert)?;
+                }
+                let verifier =
+                    WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+                        .build()?;
+                builder.with_client_cert_verifier(verifier)
+            }
+            None => builder.with_no_client_auth(),
+        };
+
+        let mut config = builder.with_single_cert(certs, key)?;
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+        Ok(config)
+    }
+
+    /// Loads the initial configuration for the server.
+    pub fn rustls_config(&self) -> Result<RustlsConfig, TlsError> {
+        Ok(RustlsConfig::from_config(Arc::new(self.server_config()?)))
+    }
+
+    /// Periodically checks the certificate, key and client CA files and swaps in a fresh
+    /// configuration when any of them changes. A failed reload keeps the current certificates.
+    pub fn spawn_reloader(self, rustls_config: RustlsConfig) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval =
+                tokio::time::interval(Duration::from_secs(self.reload_interval_secs.max(1)));
+            let mut last_modified = self.last_modified();
+
+            loop {
+                interval.tick().await;
+
+                let modified = self.last_modified();
+                if modified == last_modified {
+                    continue;
+                }
+
+                match self.server_config() {
+                    Ok(config) => {
+                        rustls_config.reload_from_config(Arc::new(config));
+                        last_modified = modified;
+                        info!(
+                            "Reloaded TLS certificates from {}",
+                            self.cert_path.display()
+                        );
+                    }
+                    Err(error) => warn!("Failed to reload TLS certificates: {error}"),
+                }
+            }
+        })
+    }
+
+    fn last_modified(&self) -> Vec<Option<SystemTime>> {
+        [
+            Some(&self.cert_path),
+            Some(&self.key_path),
+            self.client_ca_path.as_ref(),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|path| std::fs::metadata(path).and_then(|m| m.modified()).ok())
+        .collect()
+    }
+}
+
+fn open(path: &Path) -> Result<BufReader<File>, TlsError> {
+    File::open(path)
+        .map(BufReader::new)
+        .map_err(|e| TlsError::Io(path.to_path_buf(), e))
+}
+
+fn load_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>, TlsError> {
+    let certs = rustls_pemfile::certs(&mut open(path)?)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| TlsError::Io(path.to_path_buf(), e))?;
+
+    if certs.is_empty() {
+        return Err(TlsError::NoCertificates(path.to_path_buf()));
+    }
+
+    Ok(certs)
+}
+
+fn load_key(path: &Path) -> Result<PrivateKeyDer<'static>, TlsError> {
+    rustls_pemfile::private_key(&mut open(path)?)
+        .map_err(|e| TlsError::Io(path.to_path_buf(), e))?
+        .ok_or_else(|| TlsError::NoPrivateKey(path.to_path_buf()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    fn write_temp(contents: &str) -> NamedTempFile {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(contents.as_bytes()).unwrap();
+        file
+    }
+
+    fn self_signed() -> (NamedTempFile, NamedTempFile) {
+        let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        (
+            write_temp(&cert.cert.pem()),
+            write_temp(&cert.key_pair.serialize_pem()),
+        )
+    }
+
+    fn tls_config(cert: &NamedTempFile, key: &NamedTempFile) -> TlsConfig {
+        TlsConfig {
+            cert_path: cert.path().to_path_buf(),
+            key_path: key.path().to_path_buf(),
+            client_ca_path: None,
+            reload_interval_secs: 1,
+        }
+    }
+
+    #[test]
+    fn server_config_from_pem_files() {
+        let (cert, key) = self_signed();
+
+        let config = tls_config(&cert, &key).server_config().unwrap();
+
+        assert_eq!(
+            config.alpn_protocols,
+            vec![b"h2".to_vec(), b"http/1.1".to_vec()]
+        );
+    }
+
+    #[test]
+    fn server_config_with_client_ca() {
+        let (cert, key) = self_signed();
+        let mut config = tls_config(&cert, &key);
+        config.client_ca_path = Some(cert.path().to_path_buf());
+
+        assert!(config.server_config().is_ok());
+    }
+
+    #[test]
+    fn server_config_missing_key() {
+        let (cert, _) = self_signed();
+        let empty = write_temp("");
+
+        match tls_config(&cert, &empty).server_config() {
+            Err(TlsError::NoPrivateKey(path)) => assert_eq!(path, empty.path()),
+            _ => panic!("expected NoPrivateKey error"),
+        }
+    }
+
+    #[test]
+    fn server_config_missing_file() {
+        let (cert, key) = self_signed();
+        let mut config = tls_config(&cert, &key);
+        config.cert_path = PathBuf::from("/nonexistent/cert.pem");
+
+        assert!(matches!(config.server_config(), Err(TlsError::Io(_, _))));
+    }
+}
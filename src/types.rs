@@ -11,25 +11,34 @@ pub struct ServiceDiscovery {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct VersionsResponse {
     pub versions: Vec<VersionInfo>,
+    /// Shown to Terraform users, e.g. for deprecated versions.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    /// The highest stable version matching the `constraint` query, when one was given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latest: Option<String>,
 }
 
 /// Information about a specific provider version
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VersionInfo {
     pub version: String,
     pub protocols: Vec<String>,
     pub platforms: Vec<Platform>,
+    /// Why the version is deprecated, if it has been.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deprecation: Option<String>,
 }
 
 /// Platform information
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Platform {
     pub os: String,
     pub arch: String,
 }
 
 /// Provider download response
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DownloadResponse {
     pub protocols: Vec<String>,
     pub os: String,
@@ -45,14 +54,135 @@ pub struct DownloadResponse {
 pub type Package = DownloadResponse;
 
 /// GPG signing keys
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SigningKeys {
     pub gpg_public_keys: Vec<GpgPublicKey>,
 }
 
 /// GPG public key information
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct GpgPublicKey {
     pub key_id: String,
     pub ascii_armor: String,
 }
+
+/// Every release a backend holds for a provider, including the ones it cannot serve
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DiagnosticsResponse {
+    pub releases: Vec<ReleaseDiagnostic>,
+}
+
+/// Whether a release is served as a provider version, and why not
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReleaseDiagnostic {
+    pub tag: String,
+    /// The version the release is served as, when accepted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    pub accepted: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub assets: Vec<AssetDiagnostic>,
+}
+
+/// Whether a release asset is used, and why not
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AssetDiagnostic {
+    pub name: String,
+    pub accepted: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// Readiness response listing the status of each configured backend
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReadinessResponse {
+    pub ready: bool,
+    pub backends: Vec<BackendStatus>,
+}
+
+/// Result of probing a single backend
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BackendStatus {
+    pub name: String,
+    pub ready: bool,
+    pub latency_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Namespaces holding at least one provider the registry can enumerate
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NamespacesResponse {
+    pub namespaces: Vec<String>,
+}
+
+/// Providers the registry can enumerate within one namespace
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProvidersResponse {
+    pub namespace: String,
+    pub providers: Vec<String>,
+}
+
+/// What the registry knows about one provider, for portals rather than Terraform
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProviderMetadata {
+    pub namespace: String,
+    #[serde(rename = "type")]
+    pub provider_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Where the provider's source code lives.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latest_version: Option<String>,
+    /// When the latest version was published, as RFC 3339.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub published_at: Option<String>,
+    /// Platforms the latest version is built for.
+    pub platforms: Vec<Platform>,
+}
+
+/// One page of providers matching a search
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchResponse {
+    pub meta: SearchMeta,
+    pub providers: Vec<SearchResult>,
+}
+
+/// Where a page of search results sits, in the shape the public registry paginates listings
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct SearchMeta {
+    pub limit: usize,
+    pub current_offset: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_offset: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prev_offset: Option<usize>,
+}
+
+/// A provider matching a search, best matches first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchResult {
+    pub namespace: String,
+    #[serde(rename = "type")]
+    pub provider_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+/// A provider recorded in the metadata index
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IndexedProvider {
+    pub namespace: String,
+    #[serde(rename = "type")]
+    pub provider_type: String,
+    pub versions: Vec<String>,
+    /// Unix timestamp of the last successful sync
+    pub synced_at: i64,
+}